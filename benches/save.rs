@@ -0,0 +1,175 @@
+//! Loading and saving of a big synthetic save, close in size to a late game
+//! one: large party and player documents, and many area states.
+//!
+//! Run with `cargo bench --bench save`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use pathfinder_wotr_editor::json::JsonPatch;
+use pathfinder_wotr_editor::save::{SaveDestination, SaveLoader, SavingSaveGame};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::Path;
+
+const AREAS: usize = 150;
+const AREA_OBJECTS: usize = 2_000;
+const PARTY_ENTITIES: usize = 5_000;
+const PLAYER_ENTRIES: usize = 20_000;
+
+fn character(id: usize, name: &str) -> Value {
+    let stats: serde_json::Map<String, Value> = ["Strength", "Dexterity", "SkillAthletics"]
+        .iter()
+        .enumerate()
+        .map(|(n, stat)| {
+            let stat_id = (id + 1 + n).to_string();
+            let value = json!({"$id": stat_id, "Type": stat, "m_BaseValue": 12});
+            (stat.to_string(), value)
+        })
+        .collect();
+
+    json!({
+        "$id": id.to_string(),
+        "$type": "Kingmaker.EntitySystem.Entities.UnitEntityData, Assembly-CSharp",
+        "UniqueId": format!("unit-{}", id),
+        "Descriptor": {
+            "CustomName": name,
+            "Blueprint": format!("blueprint-{}", id),
+            "Progression": {"Experience": 120_000, "MythicExperience": 5},
+            "Alignment": {"m_Vector": "0.25|-0.5"},
+            "Stats": stats,
+        },
+    })
+}
+
+/// Pseudo random numbers, so the filler doesn't compress better than the
+/// game's own entities
+fn noise(seed: usize) -> u64 {
+    (seed as u64)
+        .wrapping_mul(6_364_136_223_846_793_005)
+        .wrapping_add(1_442_695_040_888_963_407)
+        .rotate_right(29)
+}
+
+/// Filler looking like the game's entities
+fn entity(id: usize) -> Value {
+    let facts: Vec<Value> = (0..8)
+        .map(|n| json!({"Blueprint": format!("{:032x}", noise(id * 8 + n)), "Rank": n}))
+        .collect();
+
+    json!({
+        "$id": id.to_string(),
+        "$type": "Kingmaker.EntitySystem.Entities.MapObjectEntityData, Assembly-CSharp",
+        "UniqueId": format!("{:016x}", noise(id)),
+        "Position": {
+            "x": noise(id + 1) as f64 / 1e15,
+            "y": 0.0,
+            "z": noise(id + 2) as f64 / -1e15,
+        },
+        "Facts": facts,
+    })
+}
+
+fn write_save(path: &Path) {
+    let mut entities: Vec<Value> = (0..6)
+        .map(|n| character(10 + n * 10, &format!("Companion {}", n)))
+        .collect();
+    entities.extend((0..PARTY_ENTITIES).map(|n| entity(1_000 + n)));
+    let party = json!({"$id": "1", "m_EntityData": entities});
+
+    let player = json!({
+        "$id": "1",
+        "Money": 1_000,
+        "m_GlobalMaps": [],
+        "Kingdom": {
+            "Resources": {"$id": "2", "m_Finances": 10, "m_Materials": 20, "m_Favors": 30},
+            "ResourcesPerTurn": {"$id": "3", "m_Finances": 1, "m_Materials": 2, "m_Favors": 3},
+            "RecruitsManager": {"$id": "4", "m_Pool": [], "m_Growth": []},
+        },
+        "m_History": (0..PLAYER_ENTRIES).map(|n| entity(100_000 + n)).collect::<Vec<_>>(),
+    });
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    let deflated = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let documents = [
+        (
+            "header.json",
+            include_str!("../samples/header.json").to_string(),
+        ),
+        ("party.json", party.to_string()),
+        ("player.json", player.to_string()),
+    ];
+    for (name, content) in documents {
+        zip.start_file(name, deflated).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+
+    for area in 0..AREAS {
+        let objects: Vec<Value> = (0..AREA_OBJECTS)
+            .map(|n| entity(area * AREA_OBJECTS + n))
+            .collect();
+
+        zip.start_file(format!("Area_{}.json", area), deflated)
+            .unwrap();
+        zip.write_all(
+            json!({"$id": "1", "Objects": objects})
+                .to_string()
+                .as_bytes(),
+        )
+        .unwrap();
+    }
+
+    zip.finish().unwrap();
+}
+
+fn save_benchmark(c: &mut Criterion) {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("save_benchmark")
+        .tempdir()
+        .unwrap();
+    let save = tmp_dir.path().join("Manual_1.zks");
+    let output = tmp_dir.path().join("Manual_1__Copy.zks");
+    write_save(&save);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let done = runtime
+        .block_on(SaveLoader::new(save.clone()).0.load())
+        .unwrap();
+
+    let saving = |with_documents: bool| {
+        let money = JsonPatch::id_at_pointer(done.player.id.clone(), "/Money".into(), 2_000.into());
+        let (saving, _) = SavingSaveGame::new(vec![money], vec![], vec![], save.clone());
+        let saving = saving
+            .with_destination(SaveDestination::Path(output.clone()))
+            .with_backups(None);
+
+        match with_documents {
+            true => saving.with_documents(done.documents.clone()),
+            false => saving,
+        }
+    };
+
+    let mut group = c.benchmark_group("save");
+    group.sample_size(10);
+
+    group.bench_function("load", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { SaveLoader::new(save.clone()).0.load().await.unwrap() })
+    });
+    group.bench_function("save", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { saving(true).save().await.unwrap() })
+    });
+    group.bench_function("save_parsing_again", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { saving(false).save().await.unwrap() })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, save_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,908 @@
+//! Headless command line interface. It reuses the same loading and saving
+//! pipeline as the GUI, but prints the model to the terminal instead of
+//! rendering it.
+
+use crate::data::{self, Character, Player, SaveFormat, SaveType};
+use crate::json::{IndexedJson, JsonError, JsonPatch, JsonPointer, Value};
+use crate::save::{
+    self, BackupPolicy, Compression, LoadingDone, NamingTemplate, PatchSet, SaveDestination,
+    SaveError, SaveLoader, SaveSummary, SavingSaveGame,
+};
+use clap::{Parser, Subcommand};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Args {
+    /// Save game to open in the editor
+    pub save: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the save games of a directory, newest first
+    List {
+        /// Directory containing the saves [default: the game's saved games directory]
+        directory: Option<PathBuf>,
+        /// Only list the saves of this type (manual, quick, auto or other)
+        #[arg(long = "type", value_name = "TYPE")]
+        save_type: Option<SaveType>,
+        /// Group the saves by campaign
+        #[arg(long)]
+        group: bool,
+        /// Only list the newest save of each campaign
+        #[arg(long, requires = "group")]
+        newest: bool,
+    },
+    /// Print the save game information
+    Info {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+    },
+    /// Print the party members and their statistics
+    Party {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+    },
+    /// Print the player money, kingdom resources and armies
+    Player {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+    },
+    /// Check the save game for problems the game may choke on: duplicate $id,
+    /// dangling or cyclic $ref, entities of unknown types and unexpected values
+    Doctor {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+    },
+    /// Edit values and write the result to a copy of the save game
+    ///
+    /// Edits are written as TARGET=VALUE, where TARGET is one of:
+    ///   name                        the save name, as shown in game
+    ///   description                 the save description
+    ///   money                       the player's money
+    ///   <character>.name            a character custom name (also renames the save's
+    ///                               character when it is the main character)
+    ///   <character>.experience      a character experience
+    ///   <character>.mythic_experience
+    ///   <character>.<stat>          a character stat base value (eg. Strength, SkillAthletics)
+    ///   player:<pointer>            any value in player.json, VALUE being JSON
+    ///   party:<pointer>             any value in party.json, VALUE being JSON
+    ///   header:<pointer>            any value in header.json, VALUE being JSON
+    #[command(verbatim_doc_comment)]
+    Set {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+        /// Edits to apply, as TARGET=VALUE
+        #[arg(required_unless_present = "patch", value_name = "TARGET=VALUE")]
+        edits: Vec<String>,
+        /// Write the edited save to this path instead of a copy next to the original
+        #[arg(short, long, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Replace the original save, after a confirmation
+        #[arg(long)]
+        in_place: bool,
+        /// Don't ask for a confirmation before replacing the original save
+        #[arg(short, long, requires = "in_place")]
+        yes: bool,
+        /// File name of the copy. Placeholders: {stem}, {name}, {date} and {n}
+        #[arg(long, value_name = "TEMPLATE", default_value = NamingTemplate::DEFAULT_FILE_NAME)]
+        file_template: String,
+        /// In-game name of the copy. Placeholders: {stem}, {name}, {date} and {n}
+        #[arg(long, value_name = "TEMPLATE", default_value = NamingTemplate::DEFAULT_SAVE_NAME)]
+        name_template: String,
+        /// Don't back up the save being replaced
+        #[arg(long, conflicts_with = "backup_dir")]
+        no_backup: bool,
+        #[command(flatten)]
+        backups: BackupArgs,
+        /// How the edited JSON files are compressed (preserve, deflate or store)
+        #[arg(long, value_name = "METHOD", default_value_t = Compression::Preserve)]
+        compression: Compression,
+        /// Edit the save even if its format isn't supported
+        #[arg(long)]
+        force: bool,
+        /// Also apply the edits of a file written by --export (a JSON Patch)
+        #[arg(long, value_name = "FILE")]
+        patch: Option<PathBuf>,
+        /// Write the edits to a file, to apply them to another save, instead of saving
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "in_place", "patch"])]
+        export: Option<PathBuf>,
+    },
+    /// List the backups of a save game, newest first
+    Backups {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+        #[command(flatten)]
+        backups: BackupArgs,
+    },
+    /// Replace a save game by one of its backups, after a confirmation.
+    /// The current save is backed up first.
+    Restore {
+        /// Path to the save game (.zks)
+        save: PathBuf,
+        /// Backup to restore [default: the latest one]
+        backup: Option<PathBuf>,
+        /// Don't ask for a confirmation
+        #[arg(short, long)]
+        yes: bool,
+        #[command(flatten)]
+        backups: BackupArgs,
+    },
+}
+
+/// Where the replaced saves are backed up
+#[derive(Debug, clap::Args)]
+pub struct BackupArgs {
+    /// Directory of the backups [default: the editor's data directory]
+    #[arg(long, value_name = "DIR")]
+    backup_dir: Option<PathBuf>,
+    /// How many backups are kept for each save
+    #[arg(long, value_name = "N", default_value_t = BackupPolicy::DEFAULT_KEEP)]
+    keep_backups: usize,
+}
+
+impl BackupArgs {
+    fn policy(self) -> Result<BackupPolicy, CliError> {
+        let directory = self
+            .backup_dir
+            .or_else(save::default_backup_dir)
+            .ok_or(CliError::NoBackupDirectory)?;
+
+        Ok(BackupPolicy {
+            directory,
+            keep: self.keep_backups,
+        })
+    }
+}
+
+/// Run the given command to completion and return the process exit code.
+pub fn run(command: Command) -> i32 {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Couldn't start the async runtime");
+
+    let mut out = std::io::stdout().lock();
+
+    match runtime.block_on(execute(command, &mut out)) {
+        Ok(()) => 0,
+        // The reading end of a pipe went away (eg. `| head`), nothing left to do
+        Err(CliError::Output(err)) if err.kind() == std::io::ErrorKind::BrokenPipe => 0,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            1
+        }
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    Save(SaveError),
+    InvalidEdit(String, String), // (edit, reason)
+    NoSaveDirectory,
+    NoBackupDirectory,
+    NoBackup(PathBuf),
+    Cancelled,
+    /// The save's format isn't supported, and editing it wasn't forced
+    ReadOnly(SaveFormat),
+    /// The number of errors found by `doctor`
+    Unhealthy(usize),
+    Output(std::io::Error),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Save(err) => write!(f, "{}", err.report()),
+            CliError::InvalidEdit(edit, reason) => write!(f, "invalid edit '{}': {}", edit, reason),
+            CliError::NoSaveDirectory => write!(
+                f,
+                "the saved games directory couldn't be found, please provide one"
+            ),
+            CliError::NoBackupDirectory => write!(
+                f,
+                "the backup directory couldn't be found, please provide one with --backup-dir"
+            ),
+            CliError::NoBackup(save) => write!(f, "{} has no backup", save.display()),
+            CliError::Cancelled => write!(f, "cancelled, the save hasn't been modified"),
+            CliError::ReadOnly(format) => write!(
+                f,
+                "the save format version {} isn't supported, use --force to edit it anyway",
+                format.version()
+            ),
+            CliError::Unhealthy(errors) => write!(f, "{} error(s) found in the save", errors),
+            CliError::Output(err) => write!(f, "couldn't write output: {}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::Output(err)
+    }
+}
+
+impl From<SaveError> for CliError {
+    fn from(err: SaveError) -> Self {
+        CliError::Save(err)
+    }
+}
+
+async fn execute(command: Command, out: &mut impl Write) -> Result<(), CliError> {
+    match command {
+        Command::List {
+            directory,
+            save_type,
+            group,
+            newest,
+        } => {
+            let directory = directory
+                .or_else(save::default_save_game_dir)
+                .ok_or(CliError::NoSaveDirectory)?;
+
+            let mut saves = save::list_saves(&directory).await?;
+            saves.retain(|s| save_type.is_none() || s.header.save_type == save_type);
+            saves.sort_by_key(|s| std::cmp::Reverse(s.modified));
+
+            if group {
+                for campaign in save::group_by_campaign(&saves) {
+                    writeln!(
+                        out,
+                        "{} [{}]",
+                        campaign.title(),
+                        campaign.game_id.unwrap_or("no game id")
+                    )?;
+
+                    let shown = if newest { 1 } else { campaign.saves.len() };
+                    for summary in campaign.saves.iter().take(shown) {
+                        write!(out, "  ")?;
+                        print_summary(out, summary)?;
+                    }
+                }
+            } else {
+                for summary in &saves {
+                    print_summary(out, summary)?;
+                }
+            }
+        }
+        Command::Info { save } => {
+            let done = load(&save).await?;
+
+            writeln!(
+                out,
+                "File:                  {}",
+                done.archive_path.display()
+            )?;
+            writeln!(out, "Name:                  {}", done.header.name)?;
+            writeln!(out, "Compatibility version: {}", done.format())?;
+            writeln!(
+                out,
+                "Type:                  {}",
+                done.header
+                    .save_type
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            )?;
+            writeln!(
+                out,
+                "Saved at:              {}",
+                done.header
+                    .system_save_time
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_else(|| "-".to_string())
+            )?;
+            writeln!(
+                out,
+                "Play time:             {}",
+                done.header
+                    .game_total_time
+                    .as_ref()
+                    .map(data::format_game_time)
+                    .unwrap_or_else(|| "-".to_string())
+            )?;
+            writeln!(
+                out,
+                "Party members:         {}",
+                done.party.characters.len()
+            )?;
+            writeln!(
+                out,
+                "Party portraits:       {}",
+                done.header
+                    .party_portraits
+                    .iter()
+                    .map(|p| p.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            writeln!(out, "Money:                 {}", done.player.money)?;
+        }
+        Command::Party { save } => {
+            let done = load(&save).await?;
+
+            for character in &done.party.characters {
+                print_character(out, character)?;
+            }
+        }
+        Command::Player { save } => {
+            let done = load(&save).await?;
+
+            print_player(out, &done.player)?;
+        }
+        Command::Doctor { save } => {
+            let issues = save::check_integrity(&save).await?;
+
+            for issue in &issues {
+                let severity = match issue.problem.is_error() {
+                    true => "error",
+                    false => "warning",
+                };
+                writeln!(out, "{}\t{}", severity, issue)?;
+            }
+
+            let errors = issues.iter().filter(|i| i.problem.is_error()).count();
+            if errors > 0 {
+                return Err(CliError::Unhealthy(errors));
+            }
+            eprintln!("No error found in {}", save.display());
+        }
+        Command::Set {
+            save,
+            edits,
+            output,
+            in_place,
+            yes,
+            file_template,
+            name_template,
+            no_backup,
+            backups,
+            compression,
+            force,
+            patch,
+            export,
+        } => {
+            let destination = match (output, in_place) {
+                (Some(path), _) => SaveDestination::Path(path),
+                (None, true) => SaveDestination::Overwrite,
+                (None, false) => SaveDestination::Copy(NamingTemplate {
+                    file_name: file_template,
+                    save_name: name_template,
+                }),
+            };
+
+            let backups = match no_backup {
+                true => None,
+                false => Some(backups.policy()?),
+            };
+
+            if destination == SaveDestination::Overwrite
+                && !yes
+                && !confirm(&format!("Replace {} with the edited save?", save.display()))?
+            {
+                return Err(CliError::Cancelled);
+            }
+
+            let done = load(&save).await?;
+
+            let format = done.format();
+            if format.is_read_only() && !force {
+                return Err(CliError::ReadOnly(format));
+            }
+
+            let mut player_patches = vec![];
+            let mut party_patches = vec![];
+            let mut header_patches = vec![];
+            for edit in &edits {
+                for parsed in parse_edit(edit, &done)? {
+                    match parsed {
+                        Edit::Player(patch) => {
+                            warn_if_shared(edit, &done.documents.player, &patch);
+                            player_patches.push(patch)
+                        }
+                        Edit::Party(patch) => {
+                            warn_if_shared(edit, &done.documents.party, &patch);
+                            party_patches.push(patch)
+                        }
+                        Edit::Header(patch) => header_patches.push(patch),
+                    }
+                }
+            }
+
+            if let Some(export) = export {
+                PatchSet::export(
+                    &done.documents,
+                    &player_patches,
+                    &party_patches,
+                    &header_patches,
+                )?
+                .write(&export)
+                .await?;
+
+                writeln!(out, "{}", export.display())?;
+                return Ok(());
+            }
+
+            let patch_set = match patch {
+                Some(path) => PatchSet::read(&path).await?,
+                None => PatchSet::default(),
+            };
+
+            let (saving, notifications) = SavingSaveGame::new(
+                player_patches,
+                party_patches,
+                header_patches,
+                done.archive_path,
+            );
+            // The documents are saved as parsed, unless the save changed on
+            // disk since (eg. the game saved in the same slot): the edits are
+            // then refused rather than applied to another game state
+            let saving = saving
+                .with_destination(destination)
+                .with_backups(backups)
+                .with_compression(compression)
+                .with_patch_set(patch_set)
+                .with_fingerprint(done.documents.fingerprint.clone())
+                .with_documents(done.documents);
+            let progress = async {
+                while let Some(step) = notifications.next().await {
+                    log::info!("Saving: {:?}", step);
+                }
+            };
+
+            let (result, ()) = futures::join!(saving.save(), progress);
+            let done = result?;
+
+            // Only the path goes to the output, to ease its use in scripts
+            eprintln!(
+                "Size: {} bytes, {} bytes before editing",
+                done.size_after, done.size_before
+            );
+            writeln!(out, "{}", done.path.display())?;
+        }
+        Command::Backups { save, backups } => {
+            let policy = backups.policy()?;
+
+            for backup in save::list_backups(&policy.directory, &save).await? {
+                writeln!(
+                    out,
+                    "{}\t{}",
+                    backup.created.format("%Y-%m-%d %H:%M:%S"),
+                    backup.path.display()
+                )?;
+            }
+        }
+        Command::Restore {
+            save,
+            backup,
+            yes,
+            backups,
+        } => {
+            let policy = backups.policy()?;
+
+            let backup = match backup {
+                Some(backup) => backup,
+                None => save::list_backups(&policy.directory, &save)
+                    .await?
+                    .into_iter()
+                    .next()
+                    .map(|b| b.path)
+                    .ok_or_else(|| CliError::NoBackup(save.clone()))?,
+            };
+
+            if !yes
+                && !confirm(&format!(
+                    "Replace {} with {}?",
+                    save.display(),
+                    backup.display()
+                ))?
+            {
+                return Err(CliError::Cancelled);
+            }
+
+            save::restore_backup(&backup, &save, Some(&policy)).await?;
+
+            writeln!(out, "{}", save.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask on the terminal, the answer defaulting to no
+fn confirm(question: &str) -> std::io::Result<bool> {
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+async fn load(path: &Path) -> Result<LoadingDone, SaveError> {
+    let (loader, notifications) = SaveLoader::new(path.to_path_buf());
+    let progress = async {
+        while let Some(step) = notifications.next().await {
+            log::info!("Loading: {}", step.description());
+        }
+    };
+
+    let (result, ()) = futures::join!(loader.load(), progress);
+
+    // Along with the output, which shouldn't be polluted
+    if let Ok(done) = &result {
+        if let Some(warning) = done.format().warning() {
+            eprintln!("warning: {}", warning);
+        }
+        for diagnostic in done.diagnostics() {
+            eprintln!("warning: skipped {}", diagnostic);
+        }
+    }
+
+    result
+}
+
+/// One tab separated line per save, to ease processing with other tools
+fn print_summary(out: &mut impl Write, summary: &SaveSummary) -> std::io::Result<()> {
+    let header = &summary.header;
+    let optional = |s: &Option<String>| s.clone().unwrap_or_else(|| "-".to_string());
+
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}",
+        header
+            .system_save_time
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_else(|| "-".to_string()),
+        header
+            .save_type
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        optional(&header.player_character_name),
+        header.name,
+        summary.path.display()
+    )
+}
+
+fn print_character(out: &mut impl Write, character: &Character) -> std::io::Result<()> {
+    writeln!(out, "{} (id {})", character.name(), character.id)?;
+    writeln!(out, "  Blueprint:         {}", character.blueprint)?;
+    writeln!(out, "  Experience:        {}", character.experience)?;
+    match character.mythic_experience {
+        Some(xp) => writeln!(out, "  Mythic experience: {}", xp)?,
+        None => writeln!(out, "  Mythic experience: -")?,
+    };
+    writeln!(
+        out,
+        "  Alignment:         {}|{}",
+        character.alignment.x, character.alignment.y
+    )?;
+
+    for stat in &character.statistics {
+        match stat.base_value {
+            Some(value) => writeln!(out, "  {:<30} {}", stat.tpe, value)?,
+            None => writeln!(out, "  {:<30} -", stat.tpe)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn print_player(out: &mut impl Write, player: &Player) -> std::io::Result<()> {
+    writeln!(out, "Money: {}", player.money)?;
+
+    if let Some(kingdom) = &player.kingdom {
+        let res = &kingdom.resources;
+        let per_turn = &kingdom.resources_per_turn;
+
+        writeln!(out, "Kingdom resources (per turn):")?;
+        writeln!(
+            out,
+            "  Finances:      {} ({})",
+            res.finances, per_turn.finances
+        )?;
+        writeln!(
+            out,
+            "  Materials:     {} ({})",
+            res.materials, per_turn.materials
+        )?;
+        writeln!(out, "  Divine Favors: {} ({})", res.favors, per_turn.favors)?;
+    }
+
+    for army in &player.armies {
+        writeln!(out, "Army {}", army.id)?;
+        writeln!(out, "  Movement Points: {}", army.movement_points)?;
+
+        for squad in &army.squads {
+            let name = crate::data::Squad::id_to_name(&squad.unit).unwrap_or(&squad.unit);
+            writeln!(out, "  {:<30} {}", name, squad.count)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+enum Edit {
+    Player(JsonPatch),
+    Party(JsonPatch),
+    Header(JsonPatch),
+}
+
+/// A single edit can touch more than one file, eg. renaming the main
+/// character also renames the character shown in the game's load menu.
+fn parse_edit(edit: &str, done: &LoadingDone) -> Result<Vec<Edit>, CliError> {
+    let LoadingDone {
+        header,
+        party,
+        player,
+        ..
+    } = done;
+    let invalid = |reason: &str| CliError::InvalidEdit(edit.to_string(), reason.to_string());
+
+    let (target, value) = edit
+        .split_once('=')
+        .ok_or_else(|| invalid("expected TARGET=VALUE"))?;
+
+    let number = || {
+        value
+            .parse::<u64>()
+            .map_err(|_| invalid("expected a positive integer"))
+    };
+
+    if let Some(pointer) = target.strip_prefix("player:") {
+        let patch = raw_patch(pointer, value).map_err(|err| invalid(&err.to_string()))?;
+        return Ok(vec![Edit::Player(patch)]);
+    }
+
+    if let Some(pointer) = target.strip_prefix("party:") {
+        let patch = raw_patch(pointer, value).map_err(|err| invalid(&err.to_string()))?;
+        return Ok(vec![Edit::Party(patch)]);
+    }
+
+    if let Some(pointer) = target.strip_prefix("header:") {
+        let patch = raw_patch(pointer, value).map_err(|err| invalid(&err.to_string()))?;
+        return Ok(vec![Edit::Header(patch)]);
+    }
+
+    if target.eq_ignore_ascii_case("name") {
+        return Ok(vec![Edit::Header(JsonPatch::string(
+            "/Name".into(),
+            value.to_string(),
+        ))]);
+    }
+
+    if target.eq_ignore_ascii_case("description") {
+        return Ok(vec![Edit::Header(JsonPatch::string(
+            "/Description".into(),
+            value.to_string(),
+        ))]);
+    }
+
+    if target.eq_ignore_ascii_case("money") {
+        return Ok(vec![Edit::Player(JsonPatch::id_at_pointer(
+            player.id.clone(),
+            "/Money".into(),
+            Value::from(number()?),
+        ))]);
+    }
+
+    let (name, field) = target
+        .rsplit_once('.')
+        .ok_or_else(|| invalid("unknown target"))?;
+    let character = party
+        .characters
+        .iter()
+        .find(|c| c.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| invalid("no party member with this name"))?;
+
+    let patch = match field.to_lowercase().as_str() {
+        "name" => {
+            // Companions use the name from their blueprint, and the
+            // patch can only replace an existing value.
+            if character.name.is_none() {
+                return Err(invalid("this character has no custom name"));
+            }
+
+            let rename = JsonPatch::id_at_pointer(
+                character.id.clone(),
+                "/Descriptor/CustomName".into(),
+                Value::from(value),
+            );
+
+            let mut edits = vec![Edit::Party(rename)];
+            if party.main_character(header).map(|c| &c.id) == Some(&character.id) {
+                edits.push(Edit::Header(JsonPatch::string(
+                    "/PlayerCharacterName".into(),
+                    value.to_string(),
+                )));
+            }
+
+            return Ok(edits);
+        }
+        "experience" => JsonPatch::id_at_pointer(
+            character.id.clone(),
+            "/Descriptor/Progression/Experience".into(),
+            Value::from(number()?),
+        ),
+        "mythic_experience" => JsonPatch::id_at_pointer(
+            character.id.clone(),
+            "/Descriptor/Progression/MythicExperience".into(),
+            Value::from(number()?),
+        ),
+        _ => {
+            let stat = character
+                .statistics
+                .iter()
+                .find(|s| s.tpe.eq_ignore_ascii_case(field))
+                .ok_or_else(|| invalid("unknown stat"))?;
+
+            if stat.base_value.is_none() {
+                return Err(invalid("this stat has no base value"));
+            }
+
+            JsonPatch::id_at_pointer(
+                stat.id.clone(),
+                "/m_BaseValue".into(),
+                Value::from(number()?),
+            )
+        }
+    };
+
+    Ok(vec![Edit::Party(patch)])
+}
+
+/// The value an edit modifies may be referenced elsewhere in the save (with
+/// a `$ref`), in which case it's modified for all of them.
+fn warn_if_shared(edit: &str, index: &IndexedJson, patch: &JsonPatch) {
+    let used_by = index.shared_with(patch);
+
+    if !used_by.is_empty() {
+        eprintln!(
+            "warning: {} modifies a value which is also used by {}",
+            edit,
+            used_by
+                .iter()
+                .map(|p| format!("'{}'", p))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+}
+
+/// Raw values are parsed as JSON, falling back to a plain string so that
+/// `player:/Name=Foo` doesn't require shell quoting gymnastics.
+fn raw_patch(pointer: &str, value: &str) -> Result<JsonPatch, JsonError> {
+    let new_value = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+
+    Ok(JsonPatch::Pointer {
+        pointer: JsonPointer::parse(pointer)?,
+        new_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Alignment, Header, Party, Stat};
+    use crate::json::Id;
+    use crate::json::IndexedJson;
+    use crate::save::{Fingerprint, SaveDocuments};
+    use std::sync::Arc;
+
+    fn fixtures() -> LoadingDone {
+        let party = Party {
+            characters: vec![Character {
+                id: Id::from("10"),
+                unique_id: None,
+                name: Some("Yaslin".to_string()),
+                blueprint: "bp".to_string(),
+                experience: 100,
+                mythic_experience: None,
+                statistics: vec![Stat {
+                    id: Id::from("11"),
+                    tpe: "Strength".to_string(),
+                    base_value: Some(12),
+                }],
+                alignment: Alignment { x: 0.0, y: 0.0 },
+            }],
+            diagnostics: vec![],
+        };
+        let player = Player {
+            id: Id::from("1"),
+            armies: vec![],
+            money: 42,
+            kingdom: None,
+            diagnostics: vec![],
+        };
+
+        LoadingDone {
+            header: Header {
+                name: "Drezen".to_string(),
+                player_character_name: Some("Yaslin".to_string()),
+                ..Default::default()
+            },
+            party,
+            player,
+            archive_path: PathBuf::from("Manual_1.zks"),
+            documents: SaveDocuments {
+                header: Arc::new(IndexedJson::new(Value::Null)),
+                party: Arc::new(IndexedJson::new(Value::Null)),
+                player: Arc::new(IndexedJson::new(Value::Null)),
+                fingerprint: Fingerprint::new(b"", None),
+                archive: Arc::from(&b""[..]),
+            },
+        }
+    }
+
+    #[test]
+    fn parse_edit_money() {
+        let done = fixtures();
+
+        let edit = parse_edit("money=1000", &done).unwrap();
+
+        assert!(matches!(
+            &edit[..],
+            [Edit::Player(JsonPatch::IdPointed { new_value, .. })] if *new_value == 1000
+        ));
+    }
+
+    #[test]
+    fn parse_edit_character_stat() {
+        let done = fixtures();
+
+        let edit = parse_edit("yaslin.strength=18", &done).unwrap();
+
+        assert!(matches!(
+            &edit[..],
+            [Edit::Party(JsonPatch::IdPointed { id, new_value, .. })]
+                if *id == Id::from("11") && *new_value == 18
+        ));
+    }
+
+    #[test]
+    fn parse_edit_rejects_unknown_targets() {
+        let done = fixtures();
+
+        assert!(parse_edit("money", &done).is_err());
+        assert!(parse_edit("money=-1", &done).is_err());
+        assert!(parse_edit("Seelah.Strength=18", &done).is_err());
+        assert!(parse_edit("Yaslin.Luck=18", &done).is_err());
+        assert!(parse_edit("player:Money=18", &done).is_err());
+    }
+
+    #[test]
+    fn parse_edit_raw_pointer() {
+        let done = fixtures();
+
+        let edit = parse_edit("player:/Name=Foo", &done).unwrap();
+
+        assert!(matches!(
+            &edit[..],
+            [Edit::Player(JsonPatch::Pointer { new_value, .. })] if *new_value == "Foo"
+        ));
+    }
+
+    #[test]
+    fn parse_edit_renaming_the_main_character() {
+        let done = fixtures();
+
+        let edit = parse_edit("Yaslin.name=Ysolde", &done).unwrap();
+
+        assert!(matches!(
+            &edit[..],
+            [
+                Edit::Party(JsonPatch::IdPointed { id, new_value, .. }),
+                Edit::Header(JsonPatch::Pointer { pointer, new_value: header_value }),
+            ] if *id == Id::from("10")
+                && *new_value == "Ysolde"
+                && *pointer == JsonPointer::from("/PlayerCharacterName")
+                && *header_value == "Ysolde"
+        ));
+    }
+}
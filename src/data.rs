@@ -1,19 +1,57 @@
 //! Data model for the save game
-use log::{debug, trace};
+use chrono::{DateTime, FixedOffset};
+use log::{debug, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
+mod compatibility;
 mod names;
 
-use crate::json::{reader, Id, IndexedJson, JsonError, Value};
+pub use compatibility::SaveFormat;
+
+use crate::json::{reader, Id, IndexedJson, JsonError, JsonPointer, Value};
+
+/// Part of a save which couldn't be read, and has been left out of the model.
+/// It is kept untouched when saving, the editor only patches what it read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: &'static str,
+    /// Where the unreadable part is in the file
+    pub pointer: JsonPointer,
+    pub error: JsonError,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at '{}': {}", self.file, self.pointer, self.error)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Party {
     pub characters: Vec<Character>,
+    /// The characters (or parts of them) which couldn't be read
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Party {
+    /// The character played by the user, found by matching the header's
+    /// `PlayerCharacterName` with the characters' custom name.
+    pub fn main_character(&self, header: &Header) -> Option<&Character> {
+        let name = header.player_character_name.as_ref()?;
+
+        self.characters
+            .iter()
+            .find(|c| c.name.as_ref() == Some(name))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Character {
     pub id: Id,
+    /// Unlike the `$id`, which are numbered anew every time the game saves,
+    /// this one identifies the character across saves.
+    pub unique_id: Option<String>,
     pub name: Option<String>,
     pub blueprint: String,
     pub experience: u64,
@@ -79,38 +117,78 @@ impl<'de> Deserialize<'de> for Alignment {
     }
 }
 
-pub fn read_party(index: &IndexedJson) -> Result<Party, JsonError> {
-    let characters = reader::pointer_as_array(&index.json, &"/m_EntityData".into())?
+/// Characters which can't be read are left out, and so are their stats which
+/// can't be read. Both are reported in the party's diagnostics.
+pub fn read_party(index: &IndexedJson, format: &SaveFormat) -> Result<Party, JsonError> {
+    let mut diagnostics = vec![];
+    let mut characters = vec![];
+
+    let entity_data = JsonPointer::from("/m_EntityData");
+    let entities = reader::pointer_as_array(&index.json, &entity_data)?
         .iter()
-        .filter(|json| {
+        .enumerate()
+        .filter(|(_, json)| {
             // Only keep the entry of type unit
             json.get("$type")
                 .and_then(|j| j.as_str())
                 .filter(|s| s == &"Kingmaker.EntitySystem.Entities.UnitEntityData, Assembly-CSharp")
                 .is_some()
-        })
-        .map(|json| read_character(index, json))
-        .collect::<Result<Vec<_>, JsonError>>()?;
+        });
+
+    for (i, json) in entities {
+        let pointer = entity_data.child(i);
+
+        match read_character(index, format, json, &pointer, &mut diagnostics) {
+            Ok(character) => characters.push(character),
+            Err(error) => {
+                warn!("Skipping the character at {}: {}", pointer, error);
+                diagnostics.push(Diagnostic {
+                    file: "party.json",
+                    pointer,
+                    error,
+                });
+            }
+        }
+    }
 
-    Ok(Party { characters })
+    Ok(Party {
+        characters,
+        diagnostics,
+    })
 }
 
-fn read_character(index: &IndexedJson, json: &Value) -> Result<Character, JsonError> {
-    let statistics = reader::pointer_as_object(json, &"/Descriptor/Stats".into())?
-        .iter()
-        .filter(|(key, _)| key != &"$id")
-        .map(|(key, value)| {
-            let path = format!("/Descriptor/Stats/{}", key).into();
-            let value = index.dereference(value, &path)?;
+fn read_character(
+    index: &IndexedJson,
+    format: &SaveFormat,
+    json: &Value,
+    pointer: &JsonPointer,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Character, JsonError> {
+    let mut statistics = vec![];
+    let stats = JsonPointer::from("/Descriptor/Stats");
+    for (key, value) in reader::pointer_as_object(json, &stats)? {
+        if key == "$id" {
+            continue;
+        }
 
+        let path = stats.child(key);
+        let stat = index.dereference(value, &path).and_then(|value| {
             trace!("Looking at {} with value {:?}", path, value);
-            let stat = serde_json::from_value(value.clone())?;
-
-            Ok(stat)
-        })
-        .collect::<Result<Vec<_>, JsonError>>()?;
+            Ok(serde_json::from_value(value.clone())?)
+        });
+
+        match stat {
+            Ok(stat) => statistics.push(stat),
+            Err(error) => diagnostics.push(Diagnostic {
+                file: "party.json",
+                pointer: pointer.join(&path),
+                error,
+            }),
+        }
+    }
 
     let id = reader::pointer_as(json, &"/$id".into())?;
+    let unique_id = reader::pointer_as(json, &"/UniqueId".into()).ok();
     let name = (match reader::pointer_as(json, &"/Descriptor/CustomName".into()) {
         Ok(name) => Ok(Some(name)),
         Err(JsonError::InvalidPointer(_)) => Ok(None),
@@ -118,13 +196,16 @@ fn read_character(index: &IndexedJson, json: &Value) -> Result<Character, JsonEr
     })?;
     let blueprint = reader::pointer_as(json, &"/Descriptor/Blueprint".into())?;
     let experience = reader::pointer_as(json, &"/Descriptor/Progression/Experience".into())?;
-    let mythic_experience =
-        reader::pointer_as(json, &"/Descriptor/Progression/MythicExperience".into());
-
-    // For now let's go with this solution. In the tutorial section that path doesn't exists
-    // (since update 0.8). Let's see how it behave once we have finished act one.
-    debug!("Read mythic experience with result {:?}", mythic_experience);
-    let mythic_experience = mythic_experience.ok();
+    // Unexpected content only loses the field, not the whole character
+    let mythic_experience = format.mythic_experience(json).unwrap_or_else(|error| {
+        diagnostics.push(Diagnostic {
+            file: "party.json",
+            pointer: pointer.join(&"/Descriptor/Progression/MythicExperience".into()),
+            error,
+        });
+        None
+    });
+    debug!("Read mythic experience {:?}", mythic_experience);
 
     // We use the latest alignment value for display purpose, but we will likely have
     // to find the latest alignment change if we want to be able to modify it.
@@ -134,6 +215,7 @@ fn read_character(index: &IndexedJson, json: &Value) -> Result<Character, JsonEr
 
     Ok(Character {
         id,
+        unique_id,
         name,
         blueprint,
         experience,
@@ -149,6 +231,8 @@ pub struct Player {
     pub armies: Vec<Army>,
     pub money: u64,
     pub kingdom: Option<Kingdom>,
+    /// The armies and kingdom which couldn't be read
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -212,74 +296,507 @@ pub struct KingdomResources {
     pub favors: u64,
 }
 
+/// Only the player's `$id` and money are required. Armies and kingdom which
+/// can't be read are left out, and reported in the player's diagnostics.
 pub fn read_player(index: &IndexedJson) -> Result<Player, JsonError> {
-    let armies = reader::pointer_as_array(&index.json, &"/m_GlobalMaps".into())?
-        .iter()
-        .map(|json| {
-            reader::pointer_as_array(json, &"/m_Armies".into())?
-                .iter()
-                .filter(|json| {
-                    // We only keep the crusaders squads
-                    json.pointer("/Data/Faction")
-                        .and_then(|v| v.as_str())
-                        .filter(|s| s == &"Crusaders")
-                        .is_some()
-                })
-                .map(|json| {
-                    let id = reader::pointer_as(json, &"/$id".into())?;
-                    let movement_points = reader::pointer_as(json, &"/MovementPoints".into())?;
-                    let squads = reader::pointer_as(json, &"/Data/m_Squads".into())?;
-
-                    Ok(Army {
-                        id,
-                        movement_points,
-                        squads,
-                    })
-                })
-                .collect::<Result<Vec<_>, JsonError>>()
+    let mut diagnostics = vec![];
+    let mut skip = |pointer: JsonPointer, error: JsonError| {
+        warn!("Skipping {} of the player: {}", pointer, error);
+        diagnostics.push(Diagnostic {
+            file: "player.json",
+            pointer,
+            error,
         })
-        .collect::<Result<Vec<_>, JsonError>>()?
-        .iter()
-        .flatten()
-        .cloned()
-        .collect::<Vec<_>>();
+    };
+
+    let mut armies = vec![];
+    let global_maps = JsonPointer::from("/m_GlobalMaps");
+    let maps: &[Value] = match reader::pointer_as_array(&index.json, &global_maps) {
+        Ok(maps) => maps,
+        Err(error) => {
+            skip(global_maps.clone(), error);
+            &[]
+        }
+    };
+
+    for (i, map) in maps.iter().enumerate() {
+        let pointer = global_maps.child(i).child("m_Armies");
+        let map_armies = match reader::pointer_as_array(map, &"/m_Armies".into()) {
+            Ok(armies) => armies,
+            Err(error) => {
+                skip(pointer, error);
+                continue;
+            }
+        };
 
-    let id = reader::pointer_as(&index.json, &"/$id".into())?; // Test that out
-    let money = reader::pointer_as(&index.json, &"/Money".into())?;
+        let crusaders = map_armies.iter().enumerate().filter(|(_, json)| {
+            // We only keep the crusaders squads
+            json.pointer("/Data/Faction")
+                .and_then(|v| v.as_str())
+                .filter(|s| s == &"Crusaders")
+                .is_some()
+        });
+
+        for (j, json) in crusaders {
+            match read_army(json) {
+                Ok(army) => armies.push(army),
+                Err(error) => skip(pointer.child(j), error),
+            }
+        }
+    }
 
     // Kingdom is actually optional
-    let kingdom = reader::pointer_as_value(&index.json, &"/Kingdom".into())
+    let kingdom_pointer = JsonPointer::from("/Kingdom");
+    let kingdom = reader::pointer_as_value(&index.json, &kingdom_pointer)
         .ok()
         .filter(|json| json.is_object())
-        .map::<Result<Kingdom, JsonError>, _>(|json| {
-            let resources = reader::pointer_as(&json, &"/Resources".into())?;
-            let resources_per_turn = reader::pointer_as(&json, &"/ResourcesPerTurn".into())?;
-            let recruits = reader::pointer_as(&json, &"/RecruitsManager".into())?;
-
-            Ok(Kingdom {
-                resources,
-                resources_per_turn,
-                recruits,
-            })
-        })
-        .transpose()?;
+        .and_then(|json| {
+            read_kingdom(&json)
+                .map_err(|error| skip(kingdom_pointer.clone(), error))
+                .ok()
+        });
+
+    let id = reader::pointer_as(&index.json, &"/$id".into())?; // Test that out
+    let money = reader::pointer_as(&index.json, &"/Money".into())?;
 
     Ok(Player {
         id,
         armies,
         money,
         kingdom,
+        diagnostics,
+    })
+}
+
+fn read_army(json: &Value) -> Result<Army, JsonError> {
+    let id = reader::pointer_as(json, &"/$id".into())?;
+    let movement_points = reader::pointer_as(json, &"/MovementPoints".into())?;
+    let squads = reader::pointer_as(json, &"/Data/m_Squads".into())?;
+
+    Ok(Army {
+        id,
+        movement_points,
+        squads,
     })
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+fn read_kingdom(json: &Value) -> Result<Kingdom, JsonError> {
+    let resources = reader::pointer_as(json, &"/Resources".into())?;
+    let resources_per_turn = reader::pointer_as(json, &"/ResourcesPerTurn".into())?;
+    let recruits = reader::pointer_as(json, &"/RecruitsManager".into())?;
+
+    Ok(Kingdom {
+        resources,
+        resources_per_turn,
+        recruits,
+    })
+}
+
+/// The save's `header.json`, which is what the game shows when listing saves.
+///
+/// Only the fields we know of are modelled here. Saving never serializes this
+/// struct back, it patches the original JSON which keeps the other fields untouched.
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct Header {
     #[serde(alias = "Name")]
     pub name: String,
+    #[serde(alias = "Description", default)]
+    pub description: Option<String>,
     #[serde(alias = "CompatibilityVersion")]
     pub compatibility_version: u64,
+    /// Versions of the game the save went through
+    #[serde(skip)]
+    pub versions: Vec<u64>,
+    #[serde(alias = "Type", default)]
+    pub save_type: Option<SaveType>,
+    #[serde(alias = "QuickSaveNumber", default)]
+    pub quick_save_number: Option<u64>,
+    #[serde(alias = "IsAutoLevelupSave", default)]
+    pub is_auto_levelup_save: bool,
+    /// Identifies a playthrough, all the saves of a campaign share it
+    #[serde(alias = "GameId", default)]
+    pub game_id: Option<String>,
+    /// Blueprint of the DLC campaign, `None` for the main campaign
+    #[serde(alias = "DlcCampaign", default)]
+    pub dlc_campaign: Option<String>,
+    #[serde(alias = "EndlessDelveSeed", default)]
+    pub endless_delve_seed: Option<i64>,
+    #[serde(alias = "PlayerCharacterName", default)]
+    pub player_character_name: Option<String>,
+    /// When the save was made, in the player's timezone
+    #[serde(alias = "SystemSaveTime", default)]
+    pub system_save_time: Option<DateTime<FixedOffset>>,
+    /// In-game date, counted from the start of the campaign
+    #[serde(alias = "GameSaveTime", default, deserialize_with = "timespan")]
+    pub game_save_time: Option<Duration>,
+    /// How long the campaign has been played
+    #[serde(alias = "GameTotalTime", default, deserialize_with = "timespan")]
+    pub game_total_time: Option<Duration>,
+    /// Blueprint id of the area the save was made in
+    #[serde(alias = "Area", default)]
+    pub area: Option<String>,
+    /// Blueprint id of the part of the area the save was made in
+    #[serde(alias = "AreaPart", default)]
+    pub area_part: Option<String>,
+    #[serde(alias = "AreaNameOverride", default)]
+    pub area_name_override: Option<String>,
+    #[serde(skip)]
+    pub party_portraits: Vec<PartyPortrait>,
+    /// The elements of `Versions` and `PartyPortraits` which couldn't be read
+    #[serde(skip)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Deserialize a .NET `TimeSpan`, formatted as `[d.]hh:mm:ss[.fffffff]`
+fn timespan<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    match <Option<String>>::deserialize(deserializer)? {
+        Some(s) => parse_timespan(&s)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+pub fn parse_timespan(s: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid format: [d.]hh:mm:ss[.fffffff] expected but {} found",
+            s
+        )
+    };
+    let number = |n: &str| n.parse::<u64>().map_err(|_| invalid());
+
+    let (days, time) = match s.split_once(':') {
+        Some((first, _)) => match first.split_once('.') {
+            Some((days, _)) => (number(days)?, &s[days.len() + 1..]),
+            None => (0, s),
+        },
+        None => return Err(invalid()),
+    };
+
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, fraction),
+        None => (time, ""),
+    };
+
+    let seconds = match time.split(':').collect::<Vec<_>>()[..] {
+        [h, m, s] => number(h)? * 3600 + number(m)? * 60 + number(s)?,
+        _ => return Err(invalid()),
+    };
+
+    // .NET has a precision of 100ns (7 digits), anything smaller is ignored
+    let nanos = if fraction.is_empty() {
+        0
+    } else if fraction.len() > 9 {
+        number(&fraction[..9])?
+    } else {
+        number(fraction)? * 10u64.pow(9 - fraction.len() as u32)
+    };
+
+    Ok(Duration::new(days * 86_400 + seconds, nanos as u32))
+}
+
+/// Format a game duration the way a player would read it, eg. `1d 16h 14m`
+pub fn format_game_time(duration: &Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{}d {}h {:02}m", days, hours, minutes)
+    } else {
+        format!("{}h {:02}m", hours, minutes)
+    }
+}
+
+/// Portrait of a party member, as displayed on the save selection screen.
+/// Either a portrait from the game (`blueprint`) or one provided by the user.
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct PartyPortrait {
+    #[serde(alias = "m_Blueprint", default)]
+    pub blueprint: Option<String>,
+    #[serde(alias = "m_Data", default)]
+    pub data: Option<PortraitData>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct PortraitData {
+    #[serde(alias = "m_CustomPortraitId", default)]
+    pub custom_portrait_id: Option<String>,
+}
+
+impl PartyPortrait {
+    /// Identifier of the user provided portrait, which is also the name of
+    /// its directory in the game's `Portraits` folder.
+    pub fn custom_portrait_id(&self) -> Option<&str> {
+        self.data.as_ref()?.custom_portrait_id.as_deref()
+    }
+
+    pub fn name(&self) -> String {
+        match (self.custom_portrait_id(), &self.blueprint) {
+            (Some(id), _) => format!("Custom portrait {}", id),
+            (None, Some(blueprint)) => names::companion_as_string(blueprint)
+                .map(str::to_string)
+                .unwrap_or_else(|| blueprint.clone()),
+            (None, None) => "Unknown portrait".to_string(),
+        }
+    }
 }
 
 pub fn read_header(index: &IndexedJson) -> Result<Header, JsonError> {
-    Ok(serde_json::from_value(index.json.clone())?)
+    let mut header = Header::deserialize(&index.json)?;
+
+    // Only shown, so what's unexpected in the lists doesn't fail the whole header
+    let mut diagnostics = vec![];
+    header.versions = read_header_list(&index.json, "Versions", &mut diagnostics);
+    header.party_portraits = read_header_list(&index.json, "PartyPortraits", &mut diagnostics);
+    header.diagnostics = diagnostics;
+
+    Ok(header)
+}
+
+/// The readable elements of the header's `key` list, which may be missing or `null`
+fn read_header_list<T: serde::de::DeserializeOwned>(
+    json: &Value,
+    key: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<T> {
+    let pointer = JsonPointer::root().child(key);
+    if matches!(json.get(key), None | Some(Value::Null)) {
+        return vec![];
+    }
+
+    let values = match reader::pointer_as_array(json, &pointer) {
+        Ok(values) => values,
+        Err(error) => {
+            diagnostics.push(Diagnostic {
+                file: "header.json",
+                pointer,
+                error,
+            });
+            return vec![];
+        }
+    };
+
+    values
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, value)| match T::deserialize(value) {
+            Ok(element) => Some(element),
+            Err(error) => {
+                diagnostics.push(Diagnostic {
+                    file: "header.json",
+                    pointer: pointer.child(idx),
+                    error: error.into(),
+                });
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum SaveType {
+    Manual,
+    Quick,
+    Auto,
+    /// Any other kind of save the game might produce
+    #[serde(other)]
+    Other,
+}
+
+impl std::fmt::Display for SaveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveType::Manual => write!(f, "Manual"),
+            SaveType::Quick => write!(f, "Quick"),
+            SaveType::Auto => write!(f, "Auto"),
+            SaveType::Other => write!(f, "Other"),
+        }
+    }
+}
+
+impl std::str::FromStr for SaveType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "manual" => Ok(SaveType::Manual),
+            "quick" => Ok(SaveType::Quick),
+            "auto" => Ok(SaveType::Auto),
+            "other" => Ok(SaveType::Other),
+            _ => Err(format!(
+                "unknown save type '{}', expected manual, quick, auto or other",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timespan_formats() {
+        assert_eq!(parse_timespan("00:00:05"), Ok(Duration::from_secs(5)));
+        assert_eq!(
+            parse_timespan("1.16:14:51.0360000"),
+            Ok(Duration::new(86_400 + 16 * 3600 + 14 * 60 + 51, 36_000_000))
+        );
+        assert_eq!(
+            parse_timespan("65.08:36:14"),
+            Ok(Duration::from_secs(65 * 86_400 + 8 * 3600 + 36 * 60 + 14))
+        );
+        assert!(parse_timespan("12").is_err());
+        assert!(parse_timespan("1.2:3").is_err());
+        assert!(parse_timespan("a.00:00:00").is_err());
+    }
+
+    #[test]
+    fn read_header_sample() {
+        let json = serde_json::from_str(include_str!("../samples/header.json")).unwrap();
+        let header = read_header(&IndexedJson::new(json)).unwrap();
+
+        assert_eq!(header.name, "Wintersun -19 Lamashan (X) 4715 -08:36:14");
+        assert_eq!(header.save_type, Some(SaveType::Manual));
+        assert_eq!(header.versions, vec![10, 20, 30]);
+        assert_eq!(header.quick_save_number, Some(0));
+        assert!(!header.is_auto_levelup_save);
+        assert_eq!(header.endless_delve_seed, Some(891548406));
+        assert_eq!(
+            header.area_part.as_deref(),
+            Some("0a5654e7dc18f074d9356009d55eb51b")
+        );
+        assert_eq!(
+            header.game_total_time.map(|d| format_game_time(&d)),
+            Some("1d 16h 14m".to_string())
+        );
+        assert_eq!(
+            header.system_save_time.map(|t| t.to_rfc3339()),
+            Some("2021-09-18T11:50:54.259659100-07:00".to_string())
+        );
+        assert_eq!(header.party_portraits.len(), 6);
+        assert!(header.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn read_header_tolerates_unexpected_lists() {
+        let read = |json: &str| read_header(&IndexedJson::new(serde_json::from_str(json).unwrap()));
+
+        let header = read(r#"{"Name":"a","CompatibilityVersion":2,"Versions":null}"#).unwrap();
+        assert!(header.versions.is_empty());
+        assert!(header.diagnostics.is_empty());
+
+        let header = read(
+            r#"{"Name":"a","CompatibilityVersion":2,"Versions":[10,"20",30],
+            "PartyPortraits":[{"m_Blueprint":"bp"},{"m_Blueprint":7}]}"#,
+        )
+        .unwrap();
+        assert_eq!(header.versions, vec![10, 30]);
+        assert_eq!(header.party_portraits.len(), 1);
+        let pointers: Vec<_> = header
+            .diagnostics
+            .iter()
+            .map(|d| d.pointer.to_string())
+            .collect();
+        assert_eq!(pointers, vec!["/Versions/1", "/PartyPortraits/1"]);
+    }
+
+    #[test]
+    fn read_party_skips_unreadable_characters() {
+        let unit = |id: u32, stats: &str| {
+            format!(
+                r#"{{"$id":"{id}","$type":"Kingmaker.EntitySystem.Entities.UnitEntityData, Assembly-CSharp",
+                "Descriptor":{{"Blueprint":"bp","Progression":{{"Experience":10}},
+                "Alignment":{{"m_Vector":"0|0"}},"Stats":{stats}}}}}"#
+            )
+        };
+        let json = format!(
+            r#"{{"$id":"1","m_EntityData":[{},{},{}]}}"#,
+            unit(
+                2,
+                r#"{"Strength":{"$id":"3","Type":"Strength","m_BaseValue":12}}"#
+            ),
+            unit(
+                4,
+                r#"{"Strength":{"$id":"5","Type":"Strength","m_BaseValue":"modded"}}"#
+            ),
+            unit(6, r#"[]"#),
+        );
+
+        let party = read_party(
+            &IndexedJson::new(serde_json::from_str(&json).unwrap()),
+            &SaveFormat::Known(2),
+        )
+        .unwrap();
+
+        assert_eq!(party.characters.len(), 2);
+        assert_eq!(party.characters[0].statistics.len(), 1);
+        // The unreadable stat is left out, not the character
+        assert_eq!(party.characters[1].id, Id::from("4"));
+        assert!(party.characters[1].statistics.is_empty());
+
+        let pointers: Vec<_> = party
+            .diagnostics
+            .iter()
+            .map(|d| d.pointer.to_string())
+            .collect();
+        assert_eq!(
+            pointers,
+            vec![
+                "/m_EntityData/1/Descriptor/Stats/Strength",
+                "/m_EntityData/2"
+            ]
+        );
+        assert_eq!(
+            party.diagnostics[1].to_string(),
+            "party.json at '/m_EntityData/2': expected an object at '/Descriptor/Stats', found array"
+        );
+    }
+
+    #[test]
+    fn read_party_keeps_characters_with_an_unreadable_mythic_experience() {
+        let json = r#"{"$id":"1","m_EntityData":[{"$id":"2",
+            "$type":"Kingmaker.EntitySystem.Entities.UnitEntityData, Assembly-CSharp",
+            "Descriptor":{"Blueprint":"bp","Progression":{"Experience":10,"MythicExperience":"modded"},
+            "Alignment":{"m_Vector":"0|0"},"Stats":{}}}]}"#;
+
+        let party = read_party(
+            &IndexedJson::new(serde_json::from_str(json).unwrap()),
+            &SaveFormat::Known(2),
+        )
+        .unwrap();
+
+        assert_eq!(party.characters.len(), 1);
+        assert_eq!(party.characters[0].mythic_experience, None);
+        assert_eq!(party.diagnostics.len(), 1);
+        assert_eq!(
+            party.diagnostics[0].pointer.to_string(),
+            "/m_EntityData/0/Descriptor/Progression/MythicExperience"
+        );
+    }
+
+    #[test]
+    fn read_player_skips_unreadable_armies() {
+        let json = r#"{"$id":"1","Money":500,"Kingdom":{"$id":"2"},"m_GlobalMaps":[{"m_Armies":[
+            {"$id":"3","MovementPoints":20.0,"Data":{"Faction":"Crusaders","m_Squads":[]}},
+            {"$id":"4","Data":{"Faction":"Crusaders","m_Squads":[]}},
+            {"$id":"5","Data":{"Faction":"Demons"}}]}]}"#;
+
+        let player = read_player(&IndexedJson::new(serde_json::from_str(json).unwrap())).unwrap();
+
+        assert_eq!(player.money, 500);
+        assert_eq!(player.armies.len(), 1);
+        assert_eq!(player.kingdom, None);
+
+        let pointers: Vec<_> = player
+            .diagnostics
+            .iter()
+            .map(|d| d.pointer.to_string())
+            .collect();
+        assert_eq!(pointers, vec!["/m_GlobalMaps/0/m_Armies/1", "/Kingdom"]);
+    }
 }
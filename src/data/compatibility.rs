@@ -0,0 +1,179 @@
+use super::Header;
+use crate::json::{reader, JsonError, Value};
+
+/// The `CompatibilityVersion`s the editor reads. 2 is the version of the save in
+/// `samples/`, the only one checked against the readers. 1 is assumed to only
+/// differ by what [SaveFormat::mythic_experience] handles, no save of that
+/// version was at hand.
+const KNOWN_VERSIONS: std::ops::RangeInclusive<u64> = 1..=2;
+
+/// The latest of the header's `Versions` the editor reads, from the same sample
+/// save (`[10, 20, 30]`). Those are the upgrades the game applied to the save,
+/// which may change its structure without a new `CompatibilityVersion`. Later
+/// ones aren't known to break anything, only never seen.
+const LATEST_KNOWN_UPGRADE: u64 = 30;
+
+/// Version of the save format, as written in the header's `CompatibilityVersion`
+/// and `Versions`.
+///
+/// The structural differences between versions are handled here, so that
+/// the readers of the model don't have to know about them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// A version the editor reads, see [KNOWN_VERSIONS]
+    Known(u64),
+    /// Written by a version of the game more recent than the editor
+    Newer(u64),
+    /// Older than any version the editor knows about
+    Unknown(u64),
+    /// A known version, upgraded by a version of the game more recent than the editor
+    Upgraded(u64, u64), // (compatibility version, latest upgrade)
+}
+
+impl SaveFormat {
+    pub fn of(header: &Header) -> SaveFormat {
+        let version = header.compatibility_version;
+        let latest_upgrade = header.versions.iter().copied().max().unwrap_or_default();
+
+        if KNOWN_VERSIONS.contains(&version) && latest_upgrade > LATEST_KNOWN_UPGRADE {
+            SaveFormat::Upgraded(version, latest_upgrade)
+        } else if KNOWN_VERSIONS.contains(&version) {
+            SaveFormat::Known(version)
+        } else if version > *KNOWN_VERSIONS.end() {
+            SaveFormat::Newer(version)
+        } else {
+            SaveFormat::Unknown(version)
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            SaveFormat::Known(v)
+            | SaveFormat::Newer(v)
+            | SaveFormat::Unknown(v)
+            | SaveFormat::Upgraded(v, _) => *v,
+        }
+    }
+
+    /// Whether the editor should refuse to save the edits by default, as it
+    /// can't tell what the game would do with them.
+    pub fn is_read_only(&self) -> bool {
+        !matches!(self, SaveFormat::Known(_))
+    }
+
+    /// What the user should know before editing a save of this format
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            SaveFormat::Known(_) => None,
+            SaveFormat::Newer(v) => Some(format!(
+                "the save format version {} is newer than the ones the editor knows of \
+                 (up to {}, from the only sample save it was checked with), it may not be \
+                 read or saved correctly",
+                v,
+                KNOWN_VERSIONS.end()
+            )),
+            SaveFormat::Unknown(v) => Some(format!(
+                "the save format version {} is unknown to the editor, it may not be read \
+                 or saved correctly",
+                v
+            )),
+            SaveFormat::Upgraded(_, upgrade) => Some(format!(
+                "the save was upgraded to the version {} by the game, newer than the ones the \
+                 editor knows of (up to {}, from the only sample save it was checked with), it \
+                 may not be read or saved correctly",
+                upgrade, LATEST_KNOWN_UPGRADE
+            )),
+        }
+    }
+
+    /// `None` when the character has no mythic experience yet: in the
+    /// tutorial section that path doesn't exist (since update 0.8).
+    pub(super) fn mythic_experience(&self, character: &Value) -> Result<Option<u64>, JsonError> {
+        let experience = reader::pointer_as(
+            character,
+            &"/Descriptor/Progression/MythicExperience".into(),
+        );
+
+        match (self, experience) {
+            (_, Ok(experience)) => Ok(Some(experience)),
+            (_, Err(JsonError::InvalidPointer(_))) => Ok(None),
+            (SaveFormat::Known(_), Err(error)) => Err(error),
+            // The path may hold something else in formats the editor doesn't know,
+            // which isn't a reason to leave the character out
+            (_, Err(error)) => {
+                log::debug!("Ignoring the mythic experience: {}", error);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SaveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveFormat::Known(v) => write!(f, "{} (supported)", v),
+            SaveFormat::Newer(v) => write!(f, "{} (newer than supported)", v),
+            SaveFormat::Unknown(v) => write!(f, "{} (unknown)", v),
+            SaveFormat::Upgraded(v, upgrade) => {
+                write!(f, "{} (upgraded to the newer {})", v, upgrade)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_the_known_versions() {
+        let format = |compatibility_version| {
+            SaveFormat::of(&Header {
+                compatibility_version,
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(format(2), SaveFormat::Known(2));
+        assert_eq!(format(3), SaveFormat::Newer(3));
+        assert_eq!(format(0), SaveFormat::Unknown(0));
+        assert!(!format(1).is_read_only());
+        assert!(format(3).is_read_only());
+        assert_eq!(format(2).warning(), None);
+
+        let upgraded = |versions: Vec<u64>| {
+            SaveFormat::of(&Header {
+                compatibility_version: 2,
+                versions,
+                ..Default::default()
+            })
+        };
+        assert_eq!(upgraded(vec![10, 20, 30]), SaveFormat::Known(2));
+        assert_eq!(upgraded(vec![10, 20, 30, 40]), SaveFormat::Upgraded(2, 40));
+        assert!(upgraded(vec![40]).is_read_only());
+    }
+
+    #[test]
+    fn mythic_experience_depends_on_the_format() {
+        let character = |progression: &str| -> Value {
+            serde_json::from_str(&format!(
+                r#"{{"Descriptor":{{"Progression":{}}}}}"#,
+                progression
+            ))
+            .unwrap()
+        };
+        let known = SaveFormat::Known(2);
+        let newer = SaveFormat::Newer(3);
+
+        assert_eq!(
+            known.mythic_experience(&character(r#"{"MythicExperience":5}"#)),
+            Ok(Some(5))
+        );
+        // Before the first mythic rank
+        assert_eq!(known.mythic_experience(&character("{}")), Ok(None));
+
+        let modified = character(r#"{"MythicExperience":{"Value":5}}"#);
+        assert!(known.mythic_experience(&modified).is_err());
+        assert_eq!(newer.mythic_experience(&modified), Ok(None));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::save::default_save_game_dir;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
@@ -20,11 +21,10 @@ impl std::fmt::Display for OpenError {
 }
 
 pub async fn open_file() -> Result<PathBuf, OpenError> {
-    let default_dir =
-        match paths::default_save_game_dir().and_then(|p| p.to_str().map(|s| s.to_owned())) {
-            Some(d) => d,
-            None => panic!("No home directory set. On what can we fallback ?"),
-        };
+    // Without a home directory, let the native dialog pick its own default location
+    let default_dir = default_save_game_dir()
+        .and_then(|p| p.to_str().map(|s| s.to_owned()))
+        .unwrap_or_default();
 
     let result: Result<Option<String>, tokio::task::JoinError> =
         tokio::task::spawn_blocking(move || {
@@ -52,54 +52,113 @@ pub async fn open_file() -> Result<PathBuf, OpenError> {
     }
 }
 
-mod paths {
-    use std::path::PathBuf;
+/// Ask where to write the edited save, starting next to the original one
+pub async fn save_file(archive_path: PathBuf) -> Result<PathBuf, OpenError> {
+    let default_path = archive_path.to_string_lossy().to_string();
 
-    /*
-    a) Windows: %systemdrive%\users\%username%\AppData\LocalLow\Owlcat Games\Pathfinder Wrath Of The Righteous\Saved Games
-    b) macOS: ~/Library/Application Support/unity.Owlcat Games.Pathfinder Wrath Of The Righteous/Saved Games/
-    c) Linux: ~/.config/unity3d/Owlcat Games/Pathfinder Wrath Of The Righteous/Saved Games/
-    */
-    pub fn default_save_game_dir() -> Option<PathBuf> {
-        os::save_game()
+    let result = tokio::task::spawn_blocking(move || {
+        tinyfiledialogs::save_file_dialog_with_filter(
+            "Save the edited save game as",
+            &default_path,
+            &["*.zks"],
+            "PF Save Files",
+        )
+    })
+    .await;
+
+    match result {
+        Ok(Some(path)) => Ok(PathBuf::from(path)),
+        Ok(None) => Err(OpenError::NoneSelected),
+        Err(e) => Err(OpenError::AsyncError(format!("{}", e))),
     }
+}
 
-    #[cfg(target_os = "windows")]
-    mod win {
-        use std::path::PathBuf;
+/// Ask for a file of edits, written by [save_patch_file]
+pub async fn open_patch_file() -> Result<PathBuf, OpenError> {
+    let result = tokio::task::spawn_blocking(move || {
+        tinyfiledialogs::open_file_dialog(
+            "Choose the edits to apply",
+            "",
+            Some((&["*.json"], "JSON Patch Files")),
+        )
+    })
+    .await;
 
-        pub fn save_game() -> Option<PathBuf> {
-            // It would be better to have directly access to a data_locallow_dir() function
-            // Unfortunately the library doesn't offer it, so instead we do a path join
-            dirs::data_local_dir().map(|h| {
-                h.join("..\\LocalLow\\Owlcat Games\\Pathfinder Wrath Of The Righteous\\Saved Games")
-            })
-        }
+    match result {
+        Ok(Some(path)) => Ok(PathBuf::from(path)),
+        Ok(None) => Err(OpenError::NoneSelected),
+        Err(e) => Err(OpenError::AsyncError(format!("{}", e))),
     }
-    #[cfg(target_os = "windows")]
-    use win as os;
+}
 
-    #[cfg(any(target_os = "macos", target_os = "ios"))]
-    mod mac {
-        use std::path::PathBuf;
+/// Ask where to write the edits, starting from `default_path`
+pub async fn save_patch_file(default_path: PathBuf) -> Result<PathBuf, OpenError> {
+    let default_path = default_path.to_string_lossy().to_string();
 
-        pub fn save_game() -> Option<PathBuf> {
-            dirs::home_dir().map(|h| h.join("Library/Application Support/unity.Owlcat Games.Pathfinder Wrath Of The Righteous/Saved Games/"))
-        }
+    let result = tokio::task::spawn_blocking(move || {
+        tinyfiledialogs::save_file_dialog_with_filter(
+            "Export the edits as",
+            &default_path,
+            &["*.json"],
+            "JSON Patch Files",
+        )
+    })
+    .await;
+
+    match result {
+        Ok(Some(path)) => Ok(PathBuf::from(path)),
+        Ok(None) => Err(OpenError::NoneSelected),
+        Err(e) => Err(OpenError::AsyncError(format!("{}", e))),
     }
-    #[cfg(any(target_os = "macos", target_os = "ios"))]
-    use mac as os;
+}
 
-    #[cfg(target_os = "linux")]
-    mod mac {
-        use std::path::PathBuf;
+/// Ask for a directory, starting from `current` when there is one
+pub async fn choose_directory(current: Option<PathBuf>) -> Result<PathBuf, OpenError> {
+    let default_dir = current
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
 
-        pub fn save_game() -> Option<PathBuf> {
-            dirs::config_dir().map(|h| {
-                h.join("unity3d/Owlcat Games/Pathfinder Wrath Of The Righteous/Saved Games/")
-            })
-        }
+    let result = tokio::task::spawn_blocking(move || {
+        tinyfiledialogs::select_folder_dialog("Choose the backup directory", &default_dir)
+    })
+    .await;
+
+    match result {
+        Ok(Some(path)) => Ok(PathBuf::from(path)),
+        Ok(None) => Err(OpenError::NoneSelected),
+        Err(e) => Err(OpenError::AsyncError(format!("{}", e))),
     }
-    #[cfg(target_os = "linux")]
-    use mac as os;
+}
+
+/// A yes/no question, answered by no when the dialog can't be shown
+pub async fn confirm(title: &'static str, message: String) -> bool {
+    tokio::task::spawn_blocking(move || {
+        tinyfiledialogs::message_box_yes_no(
+            title,
+            &message,
+            tinyfiledialogs::MessageBoxIcon::Question,
+            tinyfiledialogs::YesNo::No,
+        )
+    })
+    .await
+    .map(|answer| answer == tinyfiledialogs::YesNo::Yes)
+    .unwrap_or(false)
+}
+
+/// Show `directory` in the system's file manager
+pub async fn open_folder(directory: PathBuf) -> Result<(), OpenError> {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    // The file manager outlives the call, there is nothing to wait for
+    std::process::Command::new(program)
+        .arg(&directory)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| OpenError::AsyncError(format!("{}", e)))
 }
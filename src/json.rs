@@ -3,37 +3,190 @@
 
 use serde::{Deserialize, Serialize};
 pub use serde_json::Value;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::From;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct JsonPointer(String);
+mod operation;
+
+pub use operation::Operation;
+
+/// A JSON pointer ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)), kept as its
+/// unescaped segments. The empty pointer designates the whole document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JsonPointer {
+    segments: Vec<String>,
+}
+
+impl JsonPointer {
+    pub fn root() -> JsonPointer {
+        JsonPointer::default()
+    }
+
+    /// Parse an escaped pointer, eg. `/Descriptor/Stats/Strength`
+    pub fn parse(s: &str) -> Result<JsonPointer, JsonError> {
+        let malformed =
+            |reason: &str| JsonError::MalformedPointer(s.to_string(), reason.to_string());
+
+        if s.is_empty() {
+            return Ok(JsonPointer::root());
+        }
+
+        let rest = s
+            .strip_prefix('/')
+            .ok_or_else(|| malformed("it doesn't start with '/'"))?;
+
+        let segments = rest
+            .split('/')
+            .map(|segment| {
+                // `~` only escapes itself (`~0`) and `/` (`~1`)
+                let mut chars = segment.chars();
+                while let Some(c) = chars.next() {
+                    if c == '~' && !matches!(chars.next(), Some('0') | Some('1')) {
+                        return Err(malformed("'~' must be followed by '0' or '1'"));
+                    }
+                }
+
+                Ok(segment.replace("~1", "/").replace("~0", "~"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(JsonPointer { segments })
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The unescaped segments, from the document root
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().map(String::as_str)
+    }
+
+    /// Point to `segment` (an object key or an array index) of the current value
+    pub fn push(&mut self, segment: impl ToString) {
+        self.segments.push(segment.to_string());
+    }
+
+    /// Undo the last [push](JsonPointer::push), returning its segment
+    pub fn pop(&mut self) -> Option<String> {
+        self.segments.pop()
+    }
+
+    /// Whether this pointer designates `prefix` or something within it
+    pub fn starts_with(&self, prefix: &JsonPointer) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+
+    /// Like [push](JsonPointer::push), on a copy of the pointer
+    pub fn child(&self, segment: impl ToString) -> JsonPointer {
+        let mut child = self.clone();
+        child.push(segment);
+        child
+    }
+
+    /// The pointer to the value containing this one, `None` for the root
+    pub fn parent(&self) -> Option<JsonPointer> {
+        let mut parent = self.clone();
+        parent.pop().map(|_| parent)
+    }
+
+    /// `relative` is followed from the value this pointer points to
+    pub fn join(&self, relative: &JsonPointer) -> JsonPointer {
+        let mut joined = self.clone();
+        joined.segments.extend(relative.segments.iter().cloned());
+        joined
+    }
+
+    pub fn get<'a>(&self, json: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(json, |json, segment| match json {
+                Value::Object(map) => map.get(segment),
+                Value::Array(values) => values.get(array_index(segment)?),
+                _ => None,
+            })
+    }
+
+    pub fn get_mut<'a>(&self, json: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments
+            .iter()
+            .try_fold(json, |json, segment| match json {
+                Value::Object(map) => map.get_mut(segment),
+                Value::Array(values) => values.get_mut(array_index(segment)?),
+                _ => None,
+            })
+    }
+}
 
+/// Array indexes are written without leading zeros
+fn array_index(segment: &str) -> Option<usize> {
+    match (segment.starts_with('0') && segment.len() > 1) || segment.starts_with('+') {
+        true => None,
+        false => segment.parse().ok(),
+    }
+}
+
+/// Pointers written in the code. Panics if the pointer isn't valid, those
+/// coming from elsewhere go through [JsonPointer::parse].
 impl From<&str> for JsonPointer {
     fn from(s: &str) -> Self {
-        JsonPointer(s.to_string())
+        match JsonPointer::parse(s) {
+            Ok(pointer) => pointer,
+            Err(err) => panic!("{}", err),
+        }
     }
 }
 
-impl From<String> for JsonPointer {
-    fn from(s: String) -> Self {
-        JsonPointer(s)
+impl std::str::FromStr for JsonPointer {
+    type Err = JsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(s)
+    }
+}
+
+// Written as its escaped string, eg. in the patch sets
+impl Serialize for JsonPointer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPointer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        JsonPointer::parse(&s).map_err(serde::de::Error::custom)
     }
 }
 
 impl std::fmt::Display for JsonPointer {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        for segment in &self.segments {
+            write!(f, "/{}", segment.replace('~', "~0").replace('/', "~1"))?;
+        }
+
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize)]
 pub struct Id(String);
 
-// We allow dead code because the fields are used via
-// the Debug derivation.
-#[allow(dead_code)]
-#[derive(Debug)]
+impl From<&str> for Id {
+    fn from(s: &str) -> Self {
+        Id(s.to_string())
+    }
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum JsonError {
     ArrayExpected(JsonPointer, String),  // path and actual type
     ObjectExpected(JsonPointer, String), // path and actual type
@@ -44,35 +197,180 @@ pub enum JsonError {
     UnknownId(Id),
 
     MissingId(JsonPointer), // when there is no $id in the new value (path)
-    Deserialization(serde_json::Error),
+    MalformedPointer(String, String), // (pointer, reason)
+    TestFailed(JsonPointer), // a test operation found another value (path)
+    DuplicateId(JsonPointer, Id), // path and $id already used elsewhere
+    RollbackFailed(JsonPointer), // the document was left partly patched (path not restored)
+    Deserialization(Arc<serde_json::Error>), // shared, so that the error can be cloned
 }
 
 impl From<serde_json::Error> for JsonError {
     fn from(err: serde_json::Error) -> Self {
-        JsonError::Deserialization(err)
+        JsonError::Deserialization(Arc::new(err))
     }
 }
 
-#[derive(Debug, Clone)]
+// serde_json's errors aren't comparable, their messages are
+impl PartialEq for JsonError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (JsonError::ArrayExpected(p1, t1), JsonError::ArrayExpected(p2, t2)) => {
+                p1 == p2 && t1 == t2
+            }
+            (JsonError::ObjectExpected(p1, t1), JsonError::ObjectExpected(p2, t2)) => {
+                p1 == p2 && t1 == t2
+            }
+            (JsonError::WrongType(a1, e1), JsonError::WrongType(a2, e2)) => a1 == a2 && e1 == e2,
+            (JsonError::InvalidReference(p1, i1), JsonError::InvalidReference(p2, i2)) => {
+                p1 == p2 && i1 == i2
+            }
+            (JsonError::InvalidPointer(p1), JsonError::InvalidPointer(p2)) => p1 == p2,
+            (JsonError::UnknownId(i1), JsonError::UnknownId(i2)) => i1 == i2,
+            (JsonError::MissingId(p1), JsonError::MissingId(p2)) => p1 == p2,
+            (JsonError::MalformedPointer(p1, r1), JsonError::MalformedPointer(p2, r2)) => {
+                p1 == p2 && r1 == r2
+            }
+            (JsonError::DuplicateId(p1, i1), JsonError::DuplicateId(p2, i2)) => {
+                p1 == p2 && i1 == i2
+            }
+            (JsonError::TestFailed(p1), JsonError::TestFailed(p2)) => p1 == p2,
+            (JsonError::RollbackFailed(p1), JsonError::RollbackFailed(p2)) => p1 == p2,
+            (JsonError::Deserialization(e1), JsonError::Deserialization(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // The empty pointer designates the whole document
+        let at = |p: &JsonPointer| match p.is_root() {
+            true => "the document root".to_string(),
+            false => format!("'{}'", p),
+        };
+
+        match self {
+            JsonError::ArrayExpected(p, actual) => {
+                write!(f, "expected an array at {}, found {}", at(p), actual)
+            }
+            JsonError::ObjectExpected(p, actual) => {
+                write!(f, "expected an object at {}, found {}", at(p), actual)
+            }
+            JsonError::WrongType(actual, expected) => {
+                write!(f, "expected {}, found {}", expected, actual)
+            }
+            JsonError::InvalidReference(p, id) => write!(
+                f,
+                "the reference at {} points to the unknown $id {}",
+                at(p),
+                id
+            ),
+            JsonError::InvalidPointer(p) => write!(f, "no value at {}", at(p)),
+            JsonError::UnknownId(id) => write!(f, "no object with the $id {}", id),
+            JsonError::MissingId(p) => write!(f, "the new value at {} has no $id", at(p)),
+            JsonError::DuplicateId(p, id) => write!(
+                f,
+                "the new value at {} has the $id {}, which is already used",
+                at(p),
+                id
+            ),
+            JsonError::TestFailed(p) => write!(
+                f,
+                "the value at {} isn't the one the edits were made for",
+                at(p)
+            ),
+            JsonError::RollbackFailed(p) => write!(
+                f,
+                "the edits couldn't be undone at {}, the document is left partly edited",
+                at(p)
+            ),
+            JsonError::MalformedPointer(p, reason) => {
+                write!(f, "invalid JSON pointer '{}': {}", p, reason)
+            }
+            JsonError::Deserialization(_) => write!(f, "unexpected content"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonError::Deserialization(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct IndexedJson {
     pub json: Value,
     index: BTreeMap<Id, JsonPointer>,
+    /// Where the `$ref`s to each `$id` are, the reverse of following them
+    references: BTreeMap<Id, Vec<JsonPointer>>,
 }
 
 impl IndexedJson {
     pub fn new(json: Value) -> IndexedJson {
-        let mut index = BTreeMap::new();
-        build_index(&json, "", &mut index);
+        let nodes = Nodes::of(&json, &JsonPointer::root());
+        let mut index = IndexedJson {
+            json,
+            index: BTreeMap::new(),
+            references: BTreeMap::new(),
+        };
+        index.add_nodes(nodes);
 
-        IndexedJson { json, index }
+        index
     }
 
     /// Given an id, get the associated pointer for its JSON value
-    #[allow(dead_code)]
     pub fn pointer_for(&self, id: Id) -> Result<JsonPointer, JsonError> {
         self.index.get(&id).cloned().ok_or(JsonError::UnknownId(id))
     }
 
+    /// Where the `$ref`s to `id` are. The value with this `$id` is shared
+    /// with all of them, and modifying it modifies it for all of them.
+    pub fn referenced_by(&self, id: &Id) -> &[JsonPointer] {
+        self.references.get(id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// All the `$ref`s of the document, grouped by the `$id` they point to.
+    /// Those `$id`s may not exist in the document.
+    pub fn references(&self) -> impl Iterator<Item = (&Id, &[JsonPointer])> {
+        self.references
+            .iter()
+            .map(|(id, pointers)| (id, pointers.as_slice()))
+    }
+
+    /// Where the `$ref`s to the value `patch` modifies are, as they also see
+    /// the modification: those to the node it targets, or to any node containing it.
+    pub fn shared_with(&self, patch: &JsonPatch) -> Vec<JsonPointer> {
+        let target = match patch {
+            JsonPatch::None => return vec![],
+            JsonPatch::Id { id, .. } => self.pointer_for(id.clone()),
+            JsonPatch::IdPointed { id, pointer, .. } => {
+                self.pointer_for(id.clone()).map(|p| p.join(pointer))
+            }
+            JsonPatch::Pointer { pointer, .. } => Ok(pointer.clone()),
+        };
+
+        let mut shared = vec![];
+        let mut node = target.ok();
+        while let Some(pointer) = node {
+            let id = pointer
+                .get(&self.json)
+                .and_then(|value| value.get("$id"))
+                .and_then(Value::as_str);
+            if let Some(id) = id {
+                shared.extend_from_slice(self.referenced_by(&Id::from(id)));
+            }
+            node = pointer.parent();
+        }
+
+        shared
+    }
+
     /// Get the value following a JSON pointer `path`. If the pointed node is a JSON
     /// object containing the field `$ref`, return the JSON node with the associated
     /// `$id`.
@@ -93,69 +391,187 @@ impl IndexedJson {
             Some(reference) => self
                 .index
                 .get(&reference)
-                .and_then(|pointer| self.json.pointer(&pointer.0))
+                .and_then(|pointer| pointer.get(&self.json))
                 .ok_or_else(|| JsonError::InvalidReference(path.clone(), reference)),
             None => Ok(value),
         }
     }
 
     pub fn patch(&mut self, patch: &JsonPatch) -> Result<(), JsonError> {
-        match patch {
-            JsonPatch::None => Ok(()),
-            JsonPatch::Id { id, new_value } => {
-                // Clone the pointer to release the immutable reference to self
-                let pointer = self
-                    .index
-                    .get(id)
-                    .cloned()
-                    .ok_or_else(|| JsonError::UnknownId(id.clone()))?;
-
-                let value = self
-                    .json
-                    .pointer_mut(&pointer.0)
-                    .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
+        if let Some((pointer, new_value)) = self.target(patch)? {
+            self.replace(&pointer, new_value)?;
+        }
 
-                if !new_value.contains_key("$id") {
-                    return Err(JsonError::MissingId(pointer));
-                }
+        Ok(())
+    }
 
-                *value = Value::Object(new_value.clone());
+    /// Apply all the patches, or none of them. Every patch is checked before
+    /// the document is modified, and all those which can't be applied are
+    /// reported (by their position in `patches`).
+    pub fn patch_all<'a, I>(&mut self, patches: I) -> Result<(), Vec<PatchError>>
+    where
+        I: IntoIterator<Item = &'a JsonPatch>,
+    {
+        let mut targets = vec![];
+        let mut errors = vec![];
+        for (index, patch) in patches.into_iter().enumerate() {
+            match self.target(patch) {
+                Ok(target) => targets.extend(target.map(|target| (index, target))),
+                Err(error) => errors.push(PatchError { index, error }),
+            }
+        }
 
-                Ok(())
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // A patch may still replace what a later one targets, in which case
+        // the applied patches are undone
+        let mut applied = vec![];
+        for (index, (pointer, new_value)) in targets {
+            match self.replace(&pointer, new_value) {
+                Ok(previous) => applied.push((pointer, previous)),
+                Err(error) => {
+                    let mut errors = vec![PatchError { index, error }];
+                    if let Err(error) = self.undo(applied) {
+                        errors.push(PatchError { index, error });
+                    }
+                    return Err(errors);
+                }
             }
-            JsonPatch::Pointer { pointer, new_value } => {
-                let value = self.json.pointer_mut(&pointer.0).unwrap();
+        }
 
-                *value = new_value.clone();
+        Ok(())
+    }
+
+    /// Put back the values replaced by the patches, given in the order they were applied.
+    /// They aren't checked like new values: the document may have had dangling
+    /// references to begin with, it only has to be as it was.
+    fn undo(&mut self, applied: Vec<(JsonPointer, Value)>) -> Result<(), JsonError> {
+        // In reverse order, each value is put back where it was replaced
+        for (pointer, previous) in applied.into_iter().rev() {
+            self.swap(&pointer, previous)
+                .map_err(|_| JsonError::RollbackFailed(pointer))?;
+        }
+
+        Ok(())
+    }
+
+    /// Where `patch` writes and the value it writes there, `None` when it
+    /// doesn't write anything. The document isn't modified.
+    fn target(&self, patch: &JsonPatch) -> Result<Option<(JsonPointer, Value)>, JsonError> {
+        let (pointer, new_value) = match patch {
+            JsonPatch::None => return Ok(None),
+            JsonPatch::Id { id, new_value } => {
+                let pointer = self.pointer_for(id.clone())?;
+
+                if !new_value.contains_key("$id") {
+                    return Err(JsonError::MissingId(pointer));
+                }
 
-                Ok(())
+                (pointer, Value::Object(new_value.clone()))
             }
+            JsonPatch::Pointer { pointer, new_value } => (pointer.clone(), new_value.clone()),
             JsonPatch::IdPointed {
                 id,
                 pointer,
                 new_value,
-            } => {
-                // Clone the pointer to release the immutable reference to self
-                let id_pointer = self
-                    .index
-                    .get(id)
-                    .cloned()
-                    .ok_or_else(|| JsonError::UnknownId(id.clone()))?;
-                let separator = if pointer.0.starts_with('/') { "" } else { "/" };
-                let real_pointer = format!("{}{}{}", id_pointer.0, separator, pointer.0);
-
-                let value = self.json.pointer_mut(&real_pointer).unwrap_or_else(|| {
-                    panic!(
-                        "IdPointed ({:?}:{:?}, resolved as {}) expected a value but none found",
-                        id, pointer, real_pointer
-                    )
-                });
-
-                *value = new_value.clone();
-
-                Ok(())
+            } => (
+                self.pointer_for(id.clone())?.join(pointer),
+                new_value.clone(),
+            ),
+        };
+
+        self.check_replacement(&pointer, &new_value)?;
+
+        Ok(Some((pointer, new_value)))
+    }
+
+    /// Refuse to replace the value at `pointer` with `new_value` when it would
+    /// duplicate an `$id`, or leave a `$ref` without the `$id` it points to
+    fn check_replacement(&self, pointer: &JsonPointer, new_value: &Value) -> Result<(), JsonError> {
+        let current = pointer
+            .get(&self.json)
+            .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
+        let current = Nodes::of(current, pointer);
+        let new = Nodes::of(new_value, pointer);
+
+        let mut new_ids = BTreeSet::new();
+        for (id, path) in &new.ids {
+            let elsewhere = matches!(self.index.get(id), Some(p) if !p.starts_with(pointer));
+            if elsewhere || !new_ids.insert(id) {
+                return Err(JsonError::DuplicateId(path.clone(), id.clone()));
+            }
+        }
+
+        let removed: BTreeSet<_> = current
+            .ids
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !new_ids.contains(id))
+            .collect();
+
+        for (id, path) in &new.refs {
+            if !new_ids.contains(id) && (removed.contains(id) || !self.index.contains_key(id)) {
+                return Err(JsonError::InvalidReference(path.clone(), id.clone()));
+            }
+        }
+
+        // The references from within the replaced value are replaced with it
+        for id in removed {
+            let orphaned = self
+                .referenced_by(id)
+                .iter()
+                .find(|path| !path.starts_with(pointer));
+
+            if let Some(path) = orphaned {
+                return Err(JsonError::InvalidReference(path.clone(), id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the value at `pointer`, returning the previous one. The index
+    /// and the references are updated with the nodes of the new value.
+    fn replace(&mut self, pointer: &JsonPointer, new_value: Value) -> Result<Value, JsonError> {
+        self.check_replacement(pointer, &new_value)?;
+        self.swap(pointer, new_value)
+    }
+
+    /// [IndexedJson::replace] without checking the new value
+    fn swap(&mut self, pointer: &JsonPointer, new_value: Value) -> Result<Value, JsonError> {
+        let value = pointer
+            .get_mut(&mut self.json)
+            .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
+        let previous = std::mem::replace(value, new_value);
+
+        let previous_nodes = Nodes::of(&previous, pointer);
+        for (id, path) in previous_nodes.ids {
+            if self.index.get(&id) == Some(&path) {
+                self.index.remove(&id);
+            }
+        }
+        for (id, path) in previous_nodes.refs {
+            if let Some(pointers) = self.references.get_mut(&id) {
+                pointers.retain(|p| p != &path);
+                if pointers.is_empty() {
+                    self.references.remove(&id);
+                }
             }
         }
+        if let Some(value) = pointer.get(&self.json) {
+            self.add_nodes(Nodes::of(value, pointer));
+        }
+
+        Ok(previous)
+    }
+
+    fn add_nodes(&mut self, nodes: Nodes) {
+        self.index.extend(nodes.ids);
+        for (id, path) in nodes.refs {
+            self.references.entry(id).or_default().push(path);
+        }
     }
 
     pub fn bytes(&self) -> Result<Vec<u8>, JsonError> {
@@ -163,7 +579,27 @@ impl IndexedJson {
     }
 }
 
-#[derive(Debug)]
+/// A patch of a batch which couldn't be applied, see [IndexedJson::patch_all]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    /// Position of the patch in the batch
+    pub index: usize,
+    pub error: JsonError,
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "edit #{}: {}", self.index + 1, self.error)
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum JsonPatch {
     None,
     Id {
@@ -184,7 +620,6 @@ pub enum JsonPatch {
 impl JsonPatch {
     // Can only work with pointer, not with $id
     // When working with $id, we need an object (which would include the $id field, although we can it ourselves)
-    #[allow(dead_code)]
     pub fn u64(pointer: JsonPointer, value: u64) -> JsonPatch {
         JsonPatch::Pointer {
             pointer,
@@ -208,13 +643,12 @@ impl JsonPatch {
     }
 
     // Not sure if I want to keep the Result here or at the application site
-    #[allow(dead_code)]
     pub fn by_id(id: Id, json: Value) -> Result<JsonPatch, JsonError> {
         let mut json = match json {
             Value::Object(map) => Ok(map),
             _ => Err(JsonError::WrongType(
                 reader::json_type(&json).to_string(),
-                "Object".to_string(),
+                "object".to_string(),
             )),
         }?;
 
@@ -229,28 +663,46 @@ impl JsonPatch {
     }
 }
 
-fn build_index(json: &Value, path: &str, index: &mut BTreeMap<Id, JsonPointer>) {
-    match json {
-        Value::Array(values) => {
-            for (idx, value) in values.iter().enumerate() {
-                build_index(value, &format!("{}/{}", path, idx), index);
+/// The `$id`s and `$ref`s found in a value, with where they are
+#[derive(Default)]
+struct Nodes {
+    ids: Vec<(Id, JsonPointer)>,
+    refs: Vec<(Id, JsonPointer)>,
+}
+
+impl Nodes {
+    /// `path` is where `json` is in its document
+    fn of(json: &Value, path: &JsonPointer) -> Nodes {
+        let mut nodes = Nodes::default();
+        nodes.collect(json, &mut path.clone());
+        nodes
+    }
+
+    fn collect(&mut self, json: &Value, path: &mut JsonPointer) {
+        match json {
+            Value::Array(values) => {
+                for (idx, value) in values.iter().enumerate() {
+                    path.push(idx);
+                    self.collect(value, path);
+                    path.pop();
+                }
             }
-        }
-        Value::Object(map) => {
-            // Check if there is an $id. If so, add the id with the json pointer to the index
-            map.get("$id")
-                .and_then(|j| j.as_str())
-                .and_then(|id| index.insert(Id(id.to_string()), JsonPointer(path.to_string())));
-
-            for (key, value) in map {
-                if key == "$id" {
-                    continue;
-                };
-
-                build_index(value, &format!("{}/{}", path, key), index);
+            Value::Object(map) => {
+                if let Some(id) = map.get("$id").and_then(|j| j.as_str()) {
+                    self.ids.push((Id::from(id), path.clone()));
+                }
+                if let Some(id) = map.get("$ref").and_then(|j| j.as_str()) {
+                    self.refs.push((Id::from(id), path.clone()));
+                }
+
+                for (key, value) in map {
+                    path.push(key);
+                    self.collect(value, path);
+                    path.pop();
+                }
             }
+            _ => (),
         }
-        _ => (),
     }
 }
 
@@ -273,8 +725,8 @@ pub mod reader {
 
     // In doc: Clone the JSON value before deserialization
     pub fn pointer_as_value(json: &Value, pointer: &JsonPointer) -> Result<Value, JsonError> {
-        let json = json
-            .pointer(&pointer.0)
+        let json = pointer
+            .get(json)
             .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
 
         Ok(json.clone())
@@ -286,8 +738,8 @@ pub mod reader {
         json: &'a Value,
         pointer: &'_ JsonPointer,
     ) -> Result<&'a Vec<Value>, JsonError> {
-        let json = json
-            .pointer(&pointer.0)
+        let json = pointer
+            .get(json)
             .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
 
         json.as_array()
@@ -300,8 +752,8 @@ pub mod reader {
         json: &'a Value,
         pointer: &'_ JsonPointer,
     ) -> Result<&'a serde_json::map::Map<String, Value>, JsonError> {
-        let json = json
-            .pointer(&pointer.0)
+        let json = pointer
+            .get(json)
             .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
 
         json.as_object()
@@ -391,4 +843,337 @@ mod tests {
 
         assert_eq!(index.json, expected);
     }
+
+    #[test]
+    fn indexed_json_refuses_to_patch_missing_values() {
+        let (base, _) = fixtures();
+        let mut index = IndexedJson::new(base.clone());
+
+        let by_pointer = JsonPatch::u64("/other/missing/value".into(), 7);
+        let by_id = JsonPatch::id_at_pointer(Id::from("2"), "/missing".into(), 7.into());
+
+        assert!(matches!(
+            index.patch(&by_pointer),
+            Err(JsonError::InvalidPointer(_))
+        ));
+        assert!(matches!(
+            index.patch(&by_id),
+            Err(JsonError::InvalidPointer(_))
+        ));
+        assert_eq!(index.json, base);
+    }
+
+    #[test]
+    fn indexed_json_patches_all_or_nothing() {
+        let (base, expected) = fixtures();
+        let mut index = IndexedJson::new(base.clone());
+
+        let patches = vec![
+            JsonPatch::u64("/other/value".into(), 7),
+            JsonPatch::u64("/other/missing".into(), 7),
+            JsonPatch::id_at_pointer(Id::from("3"), "/value".into(), 7.into()),
+        ];
+        let errors = index.patch_all(&patches).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                PatchError {
+                    index: 1,
+                    error: JsonError::InvalidPointer("/other/missing".into())
+                },
+                PatchError {
+                    index: 2,
+                    error: JsonError::UnknownId(Id::from("3"))
+                },
+            ]
+        );
+        assert_eq!(index.json, base);
+
+        // The second patch replaces what the third one targets
+        let patches = vec![
+            JsonPatch::u64("/other/value".into(), 7),
+            JsonPatch::Pointer {
+                pointer: "/other".into(),
+                new_value: 1.into(),
+            },
+            JsonPatch::u64("/other/value".into(), 8),
+        ];
+        let errors = index.patch_all(&patches).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].index, 2);
+        assert_eq!(index.json, base);
+
+        index.patch_all(&patches[..1]).unwrap();
+        assert_eq!(index.json, expected);
+    }
+
+    #[test]
+    fn indexed_json_undoes_patches_of_values_with_dangling_references() {
+        let base: Value =
+            serde_json::from_str(r#"{"$id":"1","a":{"broken":{"$ref":"9"},"value":1}}"#).unwrap();
+        let mut index = IndexedJson::new(base.clone());
+
+        // The first patch drops the dangling reference, which the game wrote
+        // itself and which has to be put back when the second one fails
+        let patches = vec![
+            JsonPatch::Pointer {
+                pointer: "/a".into(),
+                new_value: 1.into(),
+            },
+            JsonPatch::u64("/a/value".into(), 2),
+        ];
+        let errors = index.patch_all(&patches).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(index.json, base);
+        assert_eq!(index.referenced_by(&Id::from("9")), &["/a/broken".into()]);
+    }
+
+    #[test]
+    fn indexed_json_keeps_its_index_up_to_date() {
+        let json = serde_json::from_str(
+            r#"{"$id":"1","a":{"$id":"2","b":{"$id":"3","value":1}},"c":{"$id":"4","value":2}}"#,
+        )
+        .unwrap();
+        let mut index = IndexedJson::new(json);
+
+        // `3` moves from /a/b to /a/moved, and `5` appears
+        let patch = JsonPatch::by_id(
+            Id::from("2"),
+            serde_json::from_str(r#"{"moved":{"$id":"3","value":1},"new":{"$id":"5","value":3}}"#)
+                .unwrap(),
+        )
+        .unwrap();
+        index.patch(&patch).unwrap();
+
+        assert_eq!(index.pointer_for(Id::from("3")), Ok("/a/moved".into()));
+        assert_eq!(index.pointer_for(Id::from("5")), Ok("/a/new".into()));
+
+        let patch = JsonPatch::id_at_pointer(Id::from("3"), "/value".into(), 7.into());
+        index.patch(&patch).unwrap();
+        assert_eq!(index.json["a"]["moved"]["value"], 7);
+
+        // Replacing a subtree drops its ids
+        let patch = JsonPatch::u64("/a".into(), 0);
+        index.patch(&patch).unwrap();
+        assert_eq!(
+            index.pointer_for(Id::from("3")),
+            Err(JsonError::UnknownId(Id::from("3")))
+        );
+    }
+
+    #[test]
+    fn indexed_json_refuses_duplicate_and_orphaned_ids() {
+        let json = serde_json::from_str(
+            r#"{"$id":"1","a":{"$id":"2","value":1},"b":{"$ref":"2"},"c":{"$id":"3"}}"#,
+        )
+        .unwrap();
+        let mut index = IndexedJson::new(json);
+        let base = index.json.clone();
+
+        let duplicate = JsonPatch::Pointer {
+            pointer: "/c".into(),
+            new_value: serde_json::from_str(r#"{"$id":"3","d":{"$id":"2"}}"#).unwrap(),
+        };
+        assert_eq!(
+            index.patch(&duplicate),
+            Err(JsonError::DuplicateId("/c/d".into(), Id::from("2")))
+        );
+
+        // `/b` still references `2`
+        let orphaning = JsonPatch::u64("/a".into(), 0);
+        assert_eq!(
+            index.patch(&orphaning),
+            Err(JsonError::InvalidReference("/b".into(), Id::from("2")))
+        );
+
+        let dangling = JsonPatch::Pointer {
+            pointer: "/c".into(),
+            new_value: serde_json::from_str(r#"{"$ref":"9"}"#).unwrap(),
+        };
+        assert_eq!(
+            index.patch(&dangling),
+            Err(JsonError::InvalidReference("/c".into(), Id::from("9")))
+        );
+
+        assert_eq!(index.json, base);
+    }
+
+    #[test]
+    fn indexed_json_keeps_track_of_the_references() {
+        let json = serde_json::from_str(
+            r#"{"$id":"1","a":{"$id":"2","value":1},"b":{"$ref":"2"},"c":[{"$ref":"2"},{"$ref":"1"}]}"#,
+        )
+        .unwrap();
+        let mut index = IndexedJson::new(json);
+
+        assert_eq!(
+            index.referenced_by(&Id::from("2")),
+            &["/b".into(), "/c/0".into()]
+        );
+        // The root, which contains the value, is referenced too
+        let stat = JsonPatch::id_at_pointer(Id::from("2"), "/value".into(), 3.into());
+        let shared: Vec<JsonPointer> = vec!["/b".into(), "/c/0".into(), "/c/1".into()];
+        assert_eq!(index.shared_with(&stat), shared);
+        assert_eq!(
+            index.shared_with(&JsonPatch::u64("/a/value".into(), 3)),
+            shared
+        );
+        assert_eq!(
+            index.shared_with(&JsonPatch::u64("/b".into(), 3)),
+            vec![JsonPointer::from("/c/1")]
+        );
+
+        // References leave with the value holding them
+        index
+            .patch(&JsonPatch::Pointer {
+                pointer: "/c".into(),
+                new_value: serde_json::from_str(r#"[{"$ref":"1"},{"$id":"3"}]"#).unwrap(),
+            })
+            .unwrap();
+        assert_eq!(index.referenced_by(&Id::from("2")), &["/b".into()]);
+        assert_eq!(index.referenced_by(&Id::from("1")), &["/c/0".into()]);
+        assert!(index.referenced_by(&Id::from("3")).is_empty());
+
+        // So `2` can go once nothing references it anymore
+        index.patch(&JsonPatch::u64("/b".into(), 0)).unwrap();
+        index.patch(&JsonPatch::u64("/a".into(), 0)).unwrap();
+        assert_eq!(index.references().count(), 1);
+    }
+
+    #[test]
+    fn json_pointers_escape_their_segments() {
+        let mut pointer = JsonPointer::root().child("m_Stats");
+        pointer.push("a/b~c");
+        pointer.push(0);
+
+        assert_eq!(pointer.to_string(), "/m_Stats/a~1b~0c/0");
+        assert_eq!(
+            JsonPointer::parse("/m_Stats/a~1b~0c/0"),
+            Ok(pointer.clone())
+        );
+        assert_eq!(
+            pointer.segments().collect::<Vec<_>>(),
+            vec!["m_Stats", "a/b~c", "0"]
+        );
+        // `~01` is an escaped `~` followed by `1`, not a `/`
+        assert_eq!(
+            JsonPointer::parse("/~01")
+                .unwrap()
+                .segments()
+                .collect::<Vec<_>>(),
+            vec!["~1"]
+        );
+
+        let parent = pointer.parent().unwrap();
+        assert_eq!(parent.to_string(), "/m_Stats/a~1b~0c");
+        assert_eq!(JsonPointer::root().parent(), None);
+        assert_eq!(
+            JsonPointer::from("/m_Stats").join(&"/a~1b~0c/0".into()),
+            pointer
+        );
+    }
+
+    #[test]
+    fn json_pointers_are_validated() {
+        assert_eq!(JsonPointer::parse(""), Ok(JsonPointer::root()));
+        assert_eq!(JsonPointer::parse("/").unwrap().to_string(), "/");
+        assert_eq!(
+            JsonPointer::parse("Money").unwrap_err().to_string(),
+            "invalid JSON pointer 'Money': it doesn't start with '/'"
+        );
+        assert!(JsonPointer::parse("/a~2").is_err());
+        assert!(JsonPointer::parse("/a~").is_err());
+    }
+
+    #[test]
+    fn pointer_literals_of_the_editor_are_valid() {
+        // `From<&str>` panics on a malformed pointer, better find them here
+        let sources = [
+            include_str!("data.rs"),
+            include_str!("data/compatibility.rs"),
+            include_str!("widgets/character.rs"),
+            include_str!("widgets/editor.rs"),
+            include_str!("widgets/explorer.rs"),
+            include_str!("widgets/header.rs"),
+            include_str!("widgets/player.rs"),
+        ];
+
+        let mut literals = vec![];
+        for source in sources {
+            literals.extend(source.match_indices("\".into()").filter_map(|(end, _)| {
+                let start = source[..end].rfind('"')? + 1;
+                Some(&source[start..end])
+            }));
+            literals.extend(source.match_indices("JsonPointer::from(\"").filter_map(
+                |(start, pattern)| {
+                    let start = start + pattern.len();
+                    let end = start + source[start..].find('"')?;
+                    Some(&source[start..end])
+                },
+            ));
+        }
+
+        // Only the literals looking like pointers, the others are values
+        let pointers: Vec<_> = literals
+            .into_iter()
+            .filter(|literal| literal.contains(['/', '~']))
+            .collect();
+        assert!(pointers.contains(&"/Descriptor/Progression/MythicExperience"));
+        assert!(pointers.contains(&"/MovementPoints"));
+        for pointer in pointers {
+            assert!(
+                JsonPointer::parse(pointer).is_ok(),
+                "invalid pointer {:?}",
+                pointer
+            );
+        }
+    }
+
+    #[test]
+    fn indexed_json_handles_keys_with_slashes() {
+        let json = serde_json::from_str(
+            r#"{"$id":"1","a/b":{"$id":"2","~":{"$id":"3","items":[{"$id":"4"}]}}}"#,
+        )
+        .unwrap();
+        let mut index = IndexedJson::new(json);
+
+        assert_eq!(
+            index.pointer_for(Id::from("4")).unwrap().to_string(),
+            "/a~1b/~0/items/0"
+        );
+
+        let patch = JsonPatch::id_at_pointer(Id::from("3"), "/items/0".into(), 7.into());
+        index.patch(&patch).unwrap();
+        assert_eq!(index.json["a/b"]["~"]["items"][0], 7);
+
+        // Array indexes don't have leading zeros
+        assert_eq!(
+            JsonPointer::from("/a~1b/~0/items/00").get(&index.json),
+            None
+        );
+    }
+
+    #[test]
+    fn json_errors_are_readable() {
+        let error = JsonError::ArrayExpected("/m_EntityData".into(), "object".to_string());
+        assert_eq!(
+            error.to_string(),
+            "expected an array at '/m_EntityData', found object"
+        );
+
+        let error = JsonError::ObjectExpected("".into(), "null".to_string());
+        assert_eq!(
+            error.to_string(),
+            "expected an object at the document root, found null"
+        );
+
+        let error = JsonError::from(serde_json::from_str::<u64>("\"ten\"").unwrap_err());
+        let source = std::error::Error::source(&error).unwrap();
+        assert!(source
+            .to_string()
+            .starts_with("invalid type: string \"ten\""));
+    }
 }
@@ -0,0 +1,366 @@
+//! Edits which can be stored and shared, as JSON Patch operations
+//! ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902)).
+//!
+//! As an extension of the RFC, an operation with an `$id` member targets its
+//! `path` relatively to the node having that `$id`, like [JsonPatch::IdPointed].
+//! `move` and `copy` aren't supported, the editor has no use for them.
+//!
+//! The `$id`s are renumbered by the game on every save, so exported operations
+//! also test what identifies the node they target (see [IDENTITY_MEMBERS]).
+
+use super::{array_index, Id, IndexedJson, JsonError, JsonPatch, JsonPointer, PatchError, Value};
+use serde::{Deserialize, Serialize};
+
+/// Members telling which node an `$id` designates, unlike the `$id` itself they
+/// don't change between saves: the unit's `UniqueId`, the `Blueprint` of a
+/// descriptor or fact, the `Type` of a stat.
+const IDENTITY_MEMBERS: [&str; 4] = ["UniqueId", "Blueprint", "Type", "m_Type"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add {
+        #[serde(rename = "$id", default, skip_serializing_if = "Option::is_none")]
+        id: Option<Id>,
+        path: JsonPointer,
+        value: Value,
+    },
+    Remove {
+        #[serde(rename = "$id", default, skip_serializing_if = "Option::is_none")]
+        id: Option<Id>,
+        path: JsonPointer,
+    },
+    Replace {
+        #[serde(rename = "$id", default, skip_serializing_if = "Option::is_none")]
+        id: Option<Id>,
+        path: JsonPointer,
+        value: Value,
+    },
+    /// Fails, and so does the whole set of operations, unless the target has this value
+    Test {
+        #[serde(rename = "$id", default, skip_serializing_if = "Option::is_none")]
+        id: Option<Id>,
+        path: JsonPointer,
+        value: Value,
+    },
+}
+
+impl Operation {
+    fn target(&self) -> (Option<&Id>, &JsonPointer) {
+        match self {
+            Operation::Add { id, path, .. }
+            | Operation::Remove { id, path }
+            | Operation::Replace { id, path, .. }
+            | Operation::Test { id, path, .. } => (id.as_ref(), path),
+        }
+    }
+}
+
+impl IndexedJson {
+    /// Apply the operations in order, or none of them: a failing operation
+    /// (eg. a `test` on a save in another state) undoes the previous ones.
+    pub fn apply(&mut self, operations: &[Operation]) -> Result<(), PatchError> {
+        let mut applied = vec![];
+
+        for (index, operation) in operations.iter().enumerate() {
+            match self.apply_one(operation) {
+                Ok(undo) => applied.extend(undo),
+                Err(error) => {
+                    // A document left partly edited is worse than the failed operation
+                    self.undo(applied)
+                        .map_err(|error| PatchError { index, error })?;
+                    return Err(PatchError { index, error });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The operations doing what `patch` does, preceded by a `test` of the
+    /// value it replaces. They can then only be applied to a save in the same
+    /// state, which matters all the more as the `$id`s differ between saves:
+    /// the node of the `$id` is tested too, see [IndexedJson::identity_tests].
+    pub fn operations(&self, patch: &JsonPatch) -> Result<Vec<Operation>, JsonError> {
+        let (id, path, value) = match patch {
+            JsonPatch::None => return Ok(vec![]),
+            JsonPatch::Id { id, new_value } => (
+                Some(id.clone()),
+                JsonPointer::root(),
+                Value::Object(new_value.clone()),
+            ),
+            JsonPatch::Pointer { pointer, new_value } => (None, pointer.clone(), new_value.clone()),
+            JsonPatch::IdPointed {
+                id,
+                pointer,
+                new_value,
+            } => (Some(id.clone()), pointer.clone(), new_value.clone()),
+        };
+
+        let target = self.resolve(id.as_ref(), &path)?;
+        let current = target
+            .get(&self.json)
+            .ok_or(JsonError::InvalidPointer(target))?;
+
+        let mut operations = match &id {
+            Some(id) => self.identity_tests(id)?,
+            None => vec![],
+        };
+        operations.push(Operation::Test {
+            id: id.clone(),
+            path: path.clone(),
+            value: current.clone(),
+        });
+        operations.push(Operation::Replace { id, path, value });
+
+        Ok(operations)
+    }
+
+    /// Tests that the node with `id` is the same one in another save. Its
+    /// [IDENTITY_MEMBERS] are tested when it has some, otherwise its location.
+    fn identity_tests(&self, id: &Id) -> Result<Vec<Operation>, JsonError> {
+        let pointer = self.pointer_for(id.clone())?;
+        let node = pointer
+            .get(&self.json)
+            .ok_or_else(|| JsonError::InvalidPointer(pointer.clone()))?;
+
+        let tests: Vec<_> = IDENTITY_MEMBERS
+            .iter()
+            .filter_map(|member| {
+                let value = node
+                    .get(member)
+                    .filter(|v| !v.is_object() && !v.is_array())?;
+                Some(Operation::Test {
+                    id: Some(id.clone()),
+                    path: JsonPointer::root().child(member),
+                    value: value.clone(),
+                })
+            })
+            .collect();
+
+        if !tests.is_empty() {
+            return Ok(tests);
+        }
+
+        Ok(vec![Operation::Test {
+            id: None,
+            path: pointer.child("$id"),
+            value: Value::from(id.to_string()),
+        }])
+    }
+
+    fn resolve(&self, id: Option<&Id>, path: &JsonPointer) -> Result<JsonPointer, JsonError> {
+        match id {
+            Some(id) => Ok(self.pointer_for(id.clone())?.join(path)),
+            None => Ok(path.clone()),
+        }
+    }
+
+    /// Returns what to put back where to undo the operation, if it modified something
+    fn apply_one(
+        &mut self,
+        operation: &Operation,
+    ) -> Result<Option<(JsonPointer, Value)>, JsonError> {
+        let (id, path) = operation.target();
+        let pointer = self.resolve(id, path)?;
+
+        match operation {
+            Operation::Test { value, .. } => match pointer.get(&self.json) {
+                Some(current) if current == value => Ok(None),
+                Some(_) => Err(JsonError::TestFailed(pointer)),
+                None => Err(JsonError::InvalidPointer(pointer)),
+            },
+            Operation::Replace { value, .. } => {
+                let previous = self.replace(&pointer, value.clone())?;
+                Ok(Some((pointer, previous)))
+            }
+            // Adding the whole document replaces it
+            Operation::Add { value, .. } if pointer.is_root() => {
+                let previous = self.replace(&pointer, value.clone())?;
+                Ok(Some((pointer, previous)))
+            }
+            Operation::Add { value, .. } => self
+                .edit_container(&pointer, |container, last| match container {
+                    Value::Object(map) => {
+                        map.insert(last.to_string(), value.clone());
+                        true
+                    }
+                    Value::Array(values) if last == "-" => {
+                        values.push(value.clone());
+                        true
+                    }
+                    Value::Array(values) => match array_index(last) {
+                        Some(i) if i <= values.len() => {
+                            values.insert(i, value.clone());
+                            true
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                })
+                .map(Some),
+            Operation::Remove { .. } => self
+                .edit_container(&pointer, |container, last| match container {
+                    Value::Object(map) => map.shift_remove(last).is_some(),
+                    Value::Array(values) => match array_index(last) {
+                        Some(i) if i < values.len() => {
+                            values.remove(i);
+                            true
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                })
+                .map(Some),
+        }
+    }
+
+    /// Modify a copy of the value containing `pointer`, given its last segment,
+    /// then replace the original with it. `edit` returns whether the segment
+    /// could be edited.
+    fn edit_container<F>(
+        &mut self,
+        pointer: &JsonPointer,
+        edit: F,
+    ) -> Result<(JsonPointer, Value), JsonError>
+    where
+        F: FnOnce(&mut Value, &str) -> bool,
+    {
+        let invalid = || JsonError::InvalidPointer(pointer.clone());
+        let parent = pointer.parent().ok_or_else(invalid)?;
+        let last = pointer.segments().last().ok_or_else(invalid)?;
+
+        let mut container = parent.get(&self.json).cloned().ok_or_else(invalid)?;
+        if !edit(&mut container, last) {
+            return Err(invalid());
+        }
+
+        // Replacing the whole container keeps the index right, even for the
+        // elements of an array moved by the edit
+        let previous = self.replace(&parent, container)?;
+
+        Ok((parent, previous))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> IndexedJson {
+        IndexedJson::new(
+            serde_json::from_str(
+                r#"{"$id":"1","Money":500,"Items":[{"$id":"2","Count":1},{"$id":"3","Count":2}]}"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn operations_are_written_as_json_patch() {
+        let operations: Vec<Operation> = serde_json::from_str(
+            r#"[{"op":"test","path":"/Money","value":500},
+            {"op":"replace","$id":"3","path":"/Count","value":5},
+            {"op":"remove","path":"/Items/0"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            operations[1],
+            Operation::Replace {
+                id: Some(Id::from("3")),
+                path: "/Count".into(),
+                value: 5.into()
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&operations[2]).unwrap(),
+            r#"{"op":"remove","path":"/Items/0"}"#
+        );
+
+        let mut index = document();
+        index.apply(&operations).unwrap();
+
+        assert_eq!(
+            index.json,
+            serde_json::json!({"$id":"1","Money":500,"Items":[{"$id":"3","Count":5}]})
+        );
+        // The remaining item moved
+        assert_eq!(index.pointer_for(Id::from("3")), Ok("/Items/0".into()));
+    }
+
+    #[test]
+    fn failing_operations_undo_the_previous_ones() {
+        let mut index = document();
+        let original = index.clone();
+
+        let operations = vec![
+            Operation::Add {
+                id: None,
+                path: "/Items/-".into(),
+                value: serde_json::json!({"$id":"4","Count":3}),
+            },
+            Operation::Replace {
+                id: None,
+                path: "/Money".into(),
+                value: 1000.into(),
+            },
+            Operation::Test {
+                id: Some(Id::from("2")),
+                path: "/Count".into(),
+                value: 7.into(),
+            },
+        ];
+        let error = index.apply(&operations).unwrap_err();
+
+        assert_eq!(error.index, 2);
+        assert_eq!(error.error, JsonError::TestFailed("/Items/0/Count".into()));
+        assert_eq!(index, original);
+    }
+
+    #[test]
+    fn patches_are_exported_with_a_test_of_their_target() {
+        let index = document();
+        let patch = JsonPatch::id_at_pointer(Id::from("2"), "/Count".into(), 4.into());
+
+        let operations = index.operations(&patch).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&operations).unwrap(),
+            serde_json::json!([
+                {"op":"test","path":"/Items/0/$id","value":"2"},
+                {"op":"test","$id":"2","path":"/Count","value":1},
+                {"op":"replace","$id":"2","path":"/Count","value":4},
+            ])
+        );
+    }
+
+    #[test]
+    fn exported_operations_refuse_nodes_renumbered_since() {
+        let json = r#"{"$id":"1","Stats":{
+            "Strength":{"$id":"2","Type":"Strength","m_BaseValue":12},
+            "Dexterity":{"$id":"3","Type":"Dexterity","m_BaseValue":12}}}"#;
+        let index = IndexedJson::new(serde_json::from_str(json).unwrap());
+        let patch = JsonPatch::id_at_pointer(Id::from("2"), "/m_BaseValue".into(), 18.into());
+        let operations = index.operations(&patch).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&operations[0]).unwrap(),
+            serde_json::json!({"op":"test","$id":"2","path":"/Type","value":"Strength"})
+        );
+
+        // In the other save, the game gave the ids in another order
+        let renumbered = json
+            .replace(r#""$id":"2""#, r#""$id":"x""#)
+            .replace(r#""$id":"3""#, r#""$id":"2""#);
+        let mut other = IndexedJson::new(serde_json::from_str(&renumbered).unwrap());
+        let original = other.clone();
+
+        let error = other.apply(&operations).unwrap_err();
+        assert_eq!(
+            error.error,
+            JsonError::TestFailed("/Stats/Dexterity/Type".into())
+        );
+        assert_eq!(other, original);
+    }
+}
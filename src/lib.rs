@@ -0,0 +1,38 @@
+//! Library behind the Pathfinder: Wrath of the Righteous save editor.
+//!
+//! It is organized in three layers:
+//!
+//! - [json] contains the utilities to work with the Unity flavoured JSON
+//!   found in save games (`$id`/`$ref` resolution and [json::JsonPatch]).
+//! - [data] is the typed model read from those JSON documents.
+//! - [save] loads a `.zks` archive into the typed model and writes patched
+//!   copies of it.
+//!
+//! ```no_run
+//! use pathfinder_wotr_editor::json::JsonPatch;
+//! use pathfinder_wotr_editor::save::{SaveError, SaveLoader, SavingSaveGame};
+//!
+//! # async fn example() -> Result<(), SaveError> {
+//! // Progress notifications are optional, it's fine to drop them.
+//! let (loader, _) = SaveLoader::new("Manual_1.zks".into());
+//! let done = loader.load().await?;
+//!
+//! let money = JsonPatch::id_at_pointer(
+//!     done.player.id.clone(),
+//!     "/Money".into(),
+//!     (done.player.money * 2).into(),
+//! );
+//!
+//! // Patching the documents read by the loader avoids parsing them again
+//! let (saving, _) = SavingSaveGame::new(vec![money], vec![], vec![], done.archive_path);
+//! let copy = saving.with_documents(done.documents).save().await?.path;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The editor executable is gated behind the default `app` feature. Tools that
+//! only want the library should depend on it with `default-features = false`.
+
+pub mod data;
+pub mod json;
+pub mod save;
@@ -1,21 +1,26 @@
 #![windows_subsystem = "windows"]
 
+use clap::Parser;
 use iced::{
-    widget::{button, column, container, progress_bar, text},
+    widget::{button, column, container, progress_bar, row, text},
     Alignment, Element, Length, Subscription, Task,
 };
 use std::path::PathBuf;
 
-mod data;
+mod cli;
 mod dialog;
-mod json;
-mod save;
 mod theme;
 mod widgets;
 
-use save::{LoadNotifications, LoadingDone, LoadingStep, SaveError, SaveLoader};
-use theme::BECKETT_FONT;
-use widgets::{EditorMessage, EditorWidget};
+// The editor is a consumer of the save game library. Re-exporting its modules at the
+// root lets the rest of the application refer to them as `crate::data` and co.
+use pathfinder_wotr_editor::{data, json, save};
+
+use save::{LoadNotifications, LoadingDone, LoadingStep, SaveError, SaveLoader, SaveSummary};
+use theme::{BECKETT_FONT, BOOKLETTER_1911};
+use widgets::{
+    BrowserAction, BrowserMessage, EditorMessage, EditorWidget, Reload, SaveBrowser, SavePreview,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -23,8 +28,15 @@ pub fn main() {
     env_logger::init();
     log::debug!("Running with version {}", VERSION);
 
+    let args = cli::Args::parse();
+
+    // A subcommand means we are running headless, no need to start the UI
+    if let Some(command) = args.command {
+        std::process::exit(cli::run(command));
+    }
+
     let window = icon_window_settings();
-    let save_file: Option<PathBuf> = std::env::args().nth(1).map(|s| s.into());
+    let save_file: Option<PathBuf> = args.save;
 
     iced::application(Main::title, Main::update, Main::view)
         .subscription(Main::subscription)
@@ -57,16 +69,22 @@ fn icon_window_settings() -> iced::window::Settings {
 
 #[derive(Debug, Clone)]
 enum MainMessage {
-    OpenFileDialog,
+    Browser(BrowserMessage),
     FileChosen(Result<PathBuf, dialog::OpenError>),
+    PreviewLoaded(Box<Result<SaveSummary, SaveError>>),
     LoadProgressed(LoadingStep),
     LoadDone(Box<Result<LoadingDone, SaveError>>),
     EditorMessage(EditorMessage),
+    /// Back to the saves browser, after a save couldn't be loaded
+    TryAnotherFile,
+    /// Back to the editor being reloaded, after the reload failed
+    BackToEditor,
     FontLoaded(Result<(), iced::font::Error>),
 }
 
 enum Main {
     Loader {
+        browser: SaveBrowser,
         open_failed: Option<dialog::OpenError>,
     },
     Loading {
@@ -74,6 +92,9 @@ enum Main {
         file_path: PathBuf,
         current_step: LoadingStep,
         failed: Option<SaveError>,
+        preview: Option<Box<SavePreview>>,
+        /// The editor being reloaded, whose edits are applied to the new one
+        previous: Option<Box<EditorWidget>>,
     },
     Loaded(Box<EditorWidget>),
 }
@@ -81,20 +102,8 @@ enum Main {
 impl Main {
     fn new(save_path: Option<PathBuf>) -> (Self, Task<MainMessage>) {
         let (component, command) = match save_path {
-            None => (Main::Loader { open_failed: None }, Task::none()),
-            Some(file_path) => {
-                let (loader, notifications) = SaveLoader::new(file_path.clone());
-
-                let component = Main::Loading {
-                    notifications,
-                    file_path,
-                    current_step: LoadingStep::Initialized,
-                    failed: None,
-                };
-                let command = Task::perform(loader.load(), |r| MainMessage::LoadDone(Box::new(r)));
-
-                (component, command)
-            }
+            None => Main::loader(),
+            Some(file_path) => Main::loading(file_path),
         };
 
         let command = Task::batch(vec![
@@ -111,6 +120,42 @@ impl Main {
         (component, command)
     }
 
+    fn loader() -> (Self, Task<MainMessage>) {
+        let (browser, command) = SaveBrowser::new(save::default_save_game_dir());
+
+        let component = Main::Loader {
+            browser,
+            open_failed: None,
+        };
+
+        (component, command.map(MainMessage::Browser))
+    }
+
+    fn loading(file_path: PathBuf) -> (Self, Task<MainMessage>) {
+        let (loader, notifications) = SaveLoader::new(file_path.clone());
+
+        let path = file_path.clone();
+        let component = Main::Loading {
+            notifications,
+            file_path,
+            current_step: LoadingStep::Initialized,
+            failed: None,
+            preview: None,
+            previous: None,
+        };
+
+        // The header and thumbnail are cheap to read, which lets us show
+        // what is being loaded while the rest of the save is parsed.
+        let command = Task::batch(vec![
+            Task::perform(async move { save::read_summary(&path).await }, |r| {
+                MainMessage::PreviewLoaded(Box::new(r))
+            }),
+            Task::perform(loader.load(), |r| MainMessage::LoadDone(Box::new(r))),
+        ]);
+
+        (component, command)
+    }
+
     fn title(&self) -> String {
         match self {
             Main::Loader { .. } => "Pathfinder WotR Editor".to_string(),
@@ -121,25 +166,45 @@ impl Main {
 
     fn update(&mut self, message: MainMessage) -> Task<MainMessage> {
         match message {
-            MainMessage::OpenFileDialog => {
-                Task::perform(dialog::open_file(), MainMessage::FileChosen)
-            }
-            MainMessage::FileChosen(Ok(file_path)) => {
-                let (loader, notifications) = SaveLoader::new(file_path.clone());
-
-                *self = Main::Loading {
-                    notifications,
-                    file_path,
-                    current_step: LoadingStep::Initialized,
-                    failed: None,
+            MainMessage::Browser(msg) => {
+                let action = match self {
+                    Main::Loader { browser, .. } => browser.update(msg),
+                    _ => BrowserAction::None,
                 };
 
-                Task::perform(loader.load(), |r| MainMessage::LoadDone(Box::new(r)))
+                match action {
+                    BrowserAction::None => Task::none(),
+                    BrowserAction::Run(task) => task.map(MainMessage::Browser),
+                    BrowserAction::Open(file_path) => {
+                        let (component, command) = Main::loading(file_path);
+                        *self = component;
+                        command
+                    }
+                    BrowserAction::OpenFileDialog => {
+                        Task::perform(dialog::open_file(), MainMessage::FileChosen)
+                    }
+                }
+            }
+            MainMessage::FileChosen(Ok(file_path)) => {
+                let (component, command) = Main::loading(file_path);
+                *self = component;
+                command
             }
             MainMessage::FileChosen(Err(error)) => {
-                *self = Main::Loader {
-                    open_failed: Some(error),
-                };
+                if let Main::Loader { open_failed, .. } = self {
+                    *open_failed = Some(error);
+                }
+                Task::none()
+            }
+            MainMessage::PreviewLoaded(result) => {
+                match (self, *result) {
+                    (Main::Loading { preview, .. }, Ok(summary)) => {
+                        *preview = Some(Box::new(SavePreview::new(&summary)));
+                    }
+                    // The loader reports its own error, the preview is only a nicety
+                    (_, Err(error)) => log::debug!("Couldn't read the save preview: {:?}", error),
+                    _ => (),
+                }
                 Task::none()
             }
             MainMessage::LoadProgressed(step) => {
@@ -154,12 +219,27 @@ impl Main {
             }
             MainMessage::LoadDone(result) => match *result {
                 Ok(done) => {
-                    *self = Main::Loaded(Box::new(EditorWidget::new(
+                    let (preview, previous) = match self {
+                        Main::Loading {
+                            preview, previous, ..
+                        } => (preview.take().map(|p| *p), previous.take()),
+                        _ => (None, None),
+                    };
+
+                    let (mut editor, mut task) = EditorWidget::new(
                         done.archive_path,
+                        done.documents,
+                        done.header,
                         done.party,
                         done.player,
-                    )));
-                    Task::none()
+                        preview,
+                    );
+                    if let Some(previous) = previous {
+                        task = Task::batch(vec![task, editor.carry_over(&previous)]);
+                    }
+
+                    *self = Main::Loaded(Box::new(editor));
+                    task.map(MainMessage::EditorMessage)
                 }
                 Err(error) => {
                     if let Main::Loading { ref mut failed, .. } = self {
@@ -169,12 +249,41 @@ impl Main {
                 }
             },
             MainMessage::EditorMessage(msg) => {
-                if let Main::Loaded(ref mut state) = self {
-                    state.update(msg).map(MainMessage::EditorMessage)
-                } else {
-                    Task::none()
+                let editor = match self {
+                    Main::Loaded(editor) => editor,
+                    _ => return Task::none(),
+                };
+
+                match msg.reload() {
+                    None => editor.update(msg).map(MainMessage::EditorMessage),
+                    Some(reload) => {
+                        let (component, command) =
+                            Main::loading(editor.archive_path().to_path_buf());
+
+                        if let Main::Loaded(editor) = std::mem::replace(self, component) {
+                            if let (Reload::KeepingEdits, Main::Loading { previous, .. }) =
+                                (reload, self)
+                            {
+                                *previous = Some(editor);
+                            }
+                        }
+                        command
+                    }
                 }
             }
+            MainMessage::TryAnotherFile => {
+                let (component, command) = Main::loader();
+                *self = component;
+                command
+            }
+            MainMessage::BackToEditor => {
+                if let Main::Loading { previous, .. } = self {
+                    if let Some(editor) = previous.take() {
+                        *self = Main::Loaded(editor);
+                    }
+                }
+                Task::none()
+            }
             MainMessage::FontLoaded(result) => {
                 if let Err(error) = result {
                     log::error!("Couldn't load font. error={error:?}");
@@ -185,9 +294,12 @@ impl Main {
         }
     }
 
-    fn view(&self) -> Element<MainMessage> {
+    fn view(&self) -> Element<'_, MainMessage> {
         match self {
-            Main::Loader { open_failed } => {
+            Main::Loader {
+                browser,
+                open_failed,
+            } => {
                 let mut layout = column(vec![])
                     .align_x(Alignment::Center)
                     .spacing(8)
@@ -196,18 +308,15 @@ impl Main {
                         text("Wrath of the Righteous Edition")
                             .size(45)
                             .font(BECKETT_FONT),
-                    )
-                    .push(
-                        button(text("Load a save game"))
-                            .on_press(MainMessage::OpenFileDialog)
-                            .padding(10),
                     );
 
                 if let Some(error) = open_failed {
                     layout = layout.push(text(format!("Loading file failed: {}", error)));
                 };
 
-                let content = container(layout).max_width(640).max_height(480);
+                layout = layout.push(browser.view().map(MainMessage::Browser));
+
+                let content = container(layout).max_width(960).padding(20);
 
                 let container = container(content)
                     .center_x(Length::Fill)
@@ -219,13 +328,38 @@ impl Main {
                 failed,
                 file_path,
                 current_step,
+                preview,
+                previous,
                 ..
             } => {
+                let mut layout = column(vec![]);
+
+                if let Some(preview) = preview {
+                    layout = layout
+                        .push(preview.thumbnail(480.0))
+                        .push(text(preview.name()).font(BOOKLETTER_1911).size(24))
+                        .push(text(preview.details()))
+                        .push(preview.portraits(48.0));
+                }
+
                 let layout = match &failed {
-                    Some(error) => column(vec![])
-                        .push(text("Loading failed"))
-                        .push(text(format!("{:?}", error))),
-                    None => column(vec![])
+                    Some(error) => {
+                        let mut actions = row(vec![]).spacing(8).push(
+                            button(text("Try another file")).on_press(MainMessage::TryAnotherFile),
+                        );
+                        if previous.is_some() {
+                            actions = actions.push(
+                                button(text("Back to the editor"))
+                                    .on_press(MainMessage::BackToEditor),
+                            );
+                        }
+
+                        layout
+                            .push(text(format!("Loading {} failed", file_path.display())))
+                            .push(text(error.report()))
+                            .push(actions)
+                    }
+                    None => layout
                         .push(text(format!(
                             "Loading {:?}",
                             file_path.file_name().expect(
@@ -255,10 +389,11 @@ impl Main {
 
     fn subscription(&self) -> Subscription<MainMessage> {
         match self {
-            Main::Loading { notifications, .. } => {
-                iced::advanced::subscription::from_recipe(notifications.clone())
-                    .map(MainMessage::LoadProgressed)
-            }
+            Main::Loading { notifications, .. } => Subscription::run_with_id(
+                std::any::TypeId::of::<LoadNotifications>(),
+                notifications.clone().into_stream(),
+            )
+            .map(MainMessage::LoadProgressed),
             Main::Loaded(state) => state.subscription().map(MainMessage::EditorMessage),
             _ => Subscription::none(),
         }
@@ -0,0 +1,303 @@
+//! Backups of the saves replaced by the editor, and their restoration.
+//!
+//! Backups are named `<original file stem>.<directory key>.<timestamp>.zks` and
+//! kept in their own directory, away from the game's saves so they don't clutter
+//! its load menu. The key tells apart the saves of the same name in different
+//! directories. Backups made in the same millisecond get a `_<n>` suffix after
+//! the timestamp.
+
+use super::SaveError;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::path::{Path, PathBuf};
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupPolicy {
+    pub directory: PathBuf,
+    /// How many backups are kept for each save, the oldest ones being removed first
+    pub keep: usize,
+}
+
+impl BackupPolicy {
+    pub const DEFAULT_KEEP: usize = 5;
+
+    /// Backups in the editor's own data directory. `None` when the user's
+    /// data directory cannot be determined.
+    pub fn in_default_directory() -> Option<BackupPolicy> {
+        Some(BackupPolicy {
+            directory: default_backup_dir()?,
+            keep: BackupPolicy::DEFAULT_KEEP,
+        })
+    }
+
+    /// Copy `save` in the backup directory, then remove the backups of the
+    /// same save going over the limit.
+    pub async fn back_up(&self, save: &Path) -> Result<Backup, SaveError> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let created = Local::now();
+        let key = backup_key(save).await;
+        let path = free_backup_path(&self.directory, &key, &created).await?;
+
+        tokio::fs::copy(save, &path).await?;
+
+        for old in list_backups(&self.directory, save)
+            .await?
+            .iter()
+            .skip(self.keep.max(1))
+        {
+            tokio::fs::remove_file(&old.path).await?;
+        }
+
+        Ok(Backup { path, created })
+    }
+}
+
+/// Where the backup of the save with `key` created at `created` goes, without
+/// replacing another one made in the same millisecond
+async fn free_backup_path(
+    directory: &Path,
+    key: &str,
+    created: &DateTime<Local>,
+) -> Result<PathBuf, SaveError> {
+    let timestamp = created.format(TIMESTAMP_FORMAT).to_string();
+    let mut path = directory.join(format!("{}.{}.zks", key, timestamp));
+
+    for n in 2.. {
+        if !tokio::fs::try_exists(&path).await? {
+            break;
+        }
+        path = directory.join(format!("{}.{}_{}.zks", key, timestamp, n));
+    }
+
+    Ok(path)
+}
+
+pub fn default_backup_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("pathfinder-wotr-editor").join("backups"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: DateTime<Local>,
+}
+
+/// The backups of `save` found in `directory`, newest first.
+pub async fn list_backups(directory: &Path, save: &Path) -> Result<Vec<Backup>, SaveError> {
+    let mut entries = match tokio::fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        // Nothing has been backed up yet
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    let prefix = format!("{}.", backup_key(save).await);
+    let mut backups = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        let timestamp = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".zks"))
+            .map(|rest| {
+                rest.split_once('_')
+                    .map_or(rest, |(timestamp, _)| timestamp)
+            });
+
+        // The prefix alone would also match saves whose name starts like ours
+        let created = timestamp
+            .and_then(|t| NaiveDateTime::parse_from_str(t, TIMESTAMP_FORMAT).ok())
+            .and_then(|t| Local.from_local_datetime(&t).earliest());
+
+        if let Some(created) = created {
+            backups.push(Backup { path, created });
+        }
+    }
+
+    backups.sort_by(|a, b| b.created.cmp(&a.created).then(b.path.cmp(&a.path)));
+
+    Ok(backups)
+}
+
+/// Replace `save` by the content of `backup`. The current save is itself
+/// backed up first, so a restoration can be undone.
+pub async fn restore_backup(
+    backup: &Path,
+    save: &Path,
+    policy: Option<&BackupPolicy>,
+) -> Result<(), SaveError> {
+    // Read first, the rotation could remove the backup being restored
+    let content = tokio::fs::read(backup).await?;
+
+    if let Some(policy) = policy {
+        if tokio::fs::metadata(save).await.is_ok() {
+            policy.back_up(save).await?;
+        }
+    }
+
+    super::write_atomically(save, content).await
+}
+
+/// `<file stem>.<directory key>`, the key being a hash of the save's
+/// directory. It's written in the backups' names, so it must not change
+/// between runs (unlike std's hasher): this is FNV-1a.
+async fn backup_key(save: &Path) -> String {
+    let stem = save
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let directory = save.parent().unwrap_or_else(|| Path::new(""));
+    // The same directory may be reached through different paths
+    let directory = tokio::fs::canonicalize(directory)
+        .await
+        .unwrap_or_else(|_| directory.to_path_buf());
+
+    let hash = directory
+        .to_string_lossy()
+        .bytes()
+        .fold(0x811c_9dc5_u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        });
+
+    format!("{}.{:08x}", stem, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn backups_are_rotated_and_restored() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("backups_are_rotated")
+            .tempdir()
+            .unwrap();
+        let save = tmp_dir.path().join("Manual_1.zks");
+        let other_save = tmp_dir.path().join("Manual_10.zks");
+        let policy = BackupPolicy {
+            directory: tmp_dir.path().join("backups"),
+            keep: 3,
+        };
+
+        std::fs::write(&other_save, "other").unwrap();
+        policy.back_up(&other_save).await.unwrap();
+
+        // Pretend the backups were made a while ago, one after the other
+        for (n, age) in [(1, 30), (2, 20), (3, 10)] {
+            std::fs::write(&save, format!("version {}", n)).unwrap();
+            let backup = policy.back_up(&save).await.unwrap();
+
+            let created = Local::now() - chrono::Duration::seconds(age);
+            let renamed = policy.directory.join(format!(
+                "{}.{}.zks",
+                backup_key(&save).await,
+                created.format(TIMESTAMP_FORMAT)
+            ));
+            std::fs::rename(backup.path, renamed).unwrap();
+        }
+
+        let backups = list_backups(&policy.directory, &save).await.unwrap();
+        let contents: Vec<_> = backups
+            .iter()
+            .map(|b| std::fs::read_to_string(&b.path).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["version 3", "version 2", "version 1"]);
+
+        // Restoring backs up the current version, and rotates the older ones away
+        std::fs::write(&save, "version 4").unwrap();
+        restore_backup(&backups[2].path, &save, Some(&policy))
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&save).unwrap(), "version 1");
+        let backups = list_backups(&policy.directory, &save).await.unwrap();
+        let contents: Vec<_> = backups
+            .iter()
+            .map(|b| std::fs::read_to_string(&b.path).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["version 4", "version 3", "version 2"]);
+
+        let others = list_backups(&policy.directory, &other_save).await.unwrap();
+        assert_eq!(others.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn saves_of_the_same_name_have_their_own_backups() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("saves_of_the_same_name")
+            .tempdir()
+            .unwrap();
+        let policy = BackupPolicy {
+            directory: tmp_dir.path().join("backups"),
+            keep: 1,
+        };
+
+        let mut saves = vec![];
+        for campaign in ["Main", "DLC"] {
+            let directory = tmp_dir.path().join(campaign);
+            std::fs::create_dir(&directory).unwrap();
+            let save = directory.join("Manual_1.zks");
+            std::fs::write(&save, campaign).unwrap();
+            policy.back_up(&save).await.unwrap();
+            saves.push(save);
+        }
+
+        // Keeping a single backup of each didn't remove the other's
+        for (save, campaign) in saves.iter().zip(["Main", "DLC"]) {
+            let backups = list_backups(&policy.directory, save).await.unwrap();
+            assert_eq!(backups.len(), 1);
+            assert_eq!(std::fs::read_to_string(&backups[0].path).unwrap(), campaign);
+        }
+    }
+
+    #[tokio::test]
+    async fn backups_of_the_same_millisecond_are_all_kept() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("backups_of_the_same_millisecond")
+            .tempdir()
+            .unwrap();
+        let save = Path::new("Manual_1.zks");
+        let key = backup_key(save).await;
+        let created = Local::now();
+
+        let mut paths = vec![];
+        for _ in 0..3 {
+            let path = free_backup_path(tmp_dir.path(), &key, &created)
+                .await
+                .unwrap();
+            std::fs::write(&path, "").unwrap();
+            paths.push(path);
+        }
+
+        assert_eq!(
+            paths[2].file_name().unwrap().to_string_lossy(),
+            format!("{}.{}_3.zks", key, created.format(TIMESTAMP_FORMAT))
+        );
+        assert_eq!(list_backups(tmp_dir.path(), save).await.unwrap().len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn restored_saves_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("restored_saves_keep_their_permissions")
+            .tempdir()
+            .unwrap();
+        let save = tmp_dir.path().join("Manual_1.zks");
+        let backup = tmp_dir.path().join("backup.zks");
+        std::fs::write(&save, "current").unwrap();
+        std::fs::write(&backup, "backed up").unwrap();
+        std::fs::set_permissions(&save, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        restore_backup(&backup, &save, None).await.unwrap();
+
+        let mode = std::fs::metadata(&save).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+    }
+}
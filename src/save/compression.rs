@@ -0,0 +1,137 @@
+//! How the JSON files rewritten by the editor are compressed in the archive.
+//!
+//! Zip archives don't record the compression level used for an entry. For
+//! deflated entries, the general purpose flags carry a hint of it (normal,
+//! maximum, fast or super fast), which is what gets preserved.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// The method and level of each file in the original save
+    #[default]
+    Preserve,
+    Deflate,
+    Store,
+}
+
+impl Compression {
+    /// The options to write a file with, `original` being how the same
+    /// file is compressed in the original save.
+    pub(super) fn options(&self, original: EntryCompression) -> zip::write::SimpleFileOptions {
+        let (method, level) = match self {
+            Compression::Preserve => match original.method {
+                zip::CompressionMethod::Stored | zip::CompressionMethod::Deflated => {
+                    (original.method, original.level)
+                }
+                method => {
+                    log::warn!("Can't compress with {}, deflating instead", method);
+                    (zip::CompressionMethod::Deflated, None)
+                }
+            },
+            Compression::Deflate => (zip::CompressionMethod::Deflated, None),
+            Compression::Store => (zip::CompressionMethod::Stored, None),
+        };
+
+        zip::write::SimpleFileOptions::default()
+            .compression_method(method)
+            .compression_level(level)
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::Preserve => write!(f, "Preserve"),
+            Compression::Deflate => write!(f, "Deflate"),
+            Compression::Store => write!(f, "Store"),
+        }
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "preserve" => Ok(Compression::Preserve),
+            "deflate" => Ok(Compression::Deflate),
+            "store" => Ok(Compression::Store),
+            _ => Err(format!(
+                "unknown compression '{}', expected preserve, deflate or store",
+                s
+            )),
+        }
+    }
+}
+
+/// How a file is compressed in an archive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct EntryCompression {
+    pub method: zip::CompressionMethod,
+    /// `None` for the method's default level
+    pub level: Option<i64>,
+}
+
+impl EntryCompression {
+    /// Read from the archive's central directory. `archive` must be the
+    /// bytes `entry` was read from.
+    pub(super) fn of(entry: &zip::read::ZipFile<'_>, archive: &[u8]) -> EntryCompression {
+        let method = entry.compression();
+
+        // Flags come after the signature, and the versions made by and needed
+        let offset = entry.central_header_start() as usize + 8;
+        let flags = archive
+            .get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .unwrap_or_default();
+
+        let level = match (method, (flags >> 1) & 0b11) {
+            (zip::CompressionMethod::Deflated, 0b01) => Some(9),
+            (zip::CompressionMethod::Deflated, 0b10) => Some(2),
+            (zip::CompressionMethod::Deflated, 0b11) => Some(1),
+            _ => None,
+        };
+
+        EntryCompression { method, level }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn parse_compression() {
+        assert_eq!("Deflate".parse(), Ok(Compression::Deflate));
+        assert_eq!("store".parse(), Ok(Compression::Store));
+        assert!("zstd".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn entry_compression_reads_the_level_hint() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("party.json", options).unwrap();
+        zip.write_all(b"{}").unwrap();
+        let mut bytes = zip.finish().unwrap().into_inner();
+
+        // The zip crate doesn't set the hint, mark the entry as maximum compression
+        let central_directory = bytes
+            .windows(4)
+            .position(|w| w == [0x50, 0x4b, 0x01, 0x02])
+            .unwrap();
+        bytes[central_directory + 8] |= 0b010;
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(&bytes[..])).unwrap();
+        let entry = archive.by_name("party.json").unwrap();
+
+        assert_eq!(
+            EntryCompression::of(&entry, &bytes),
+            EntryCompression {
+                method: zip::CompressionMethod::Deflated,
+                level: Some(9),
+            }
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! What a save looked like on disk when it was loaded, to notice when the game
+//! (or anything else) replaced it before the edits are saved.
+
+use super::SaveError;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    len: u64,
+    // Only compared within a single run of the editor, so the hasher
+    // not being stable across Rust versions doesn't matter.
+    hash: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Fingerprint {
+    pub fn new(content: &[u8], modified: Option<SystemTime>) -> Fingerprint {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content);
+
+        Fingerprint {
+            len: content.len() as u64,
+            hash: hasher.finish(),
+            modified,
+        }
+    }
+
+    /// The fingerprint of the file currently at `path`
+    pub async fn read(path: &Path) -> Result<Fingerprint, SaveError> {
+        let modified = tokio::fs::metadata(path).await?.modified().ok();
+        let content = tokio::fs::read(path).await?;
+
+        Ok(Fingerprint::new(&content, modified))
+    }
+
+    /// When the file was last modified, if the platform tracks it
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Whether both fingerprints are of the same content. Only touching the
+    /// file (eg. copying it back) doesn't count as a modification.
+    pub fn same_content(&self, other: &Fingerprint) -> bool {
+        self.len == other.len && self.hash == other.hash
+    }
+}
@@ -0,0 +1,352 @@
+//! Validation of the JSON entries of a save, to find what the game may choke on.
+//!
+//! Besides the structure of the references (`$id` and `$ref`), the files the
+//! editor models are read with the typed readers to find unexpected values.
+
+use super::SaveError;
+use crate::data::{self, Diagnostic, SaveFormat};
+use crate::json::{Id, IndexedJson, JsonError, JsonPointer, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The assemblies the game ships, which define the types of what it saves.
+/// Types of other assemblies come from mods, and can't be loaded once the mod
+/// is removed.
+const GAME_ASSEMBLIES: [&str; 5] = [
+    "Assembly-CSharp",
+    "mscorlib",
+    "System",
+    "System.Core",
+    "UnityEngine.CoreModule",
+];
+
+/// Prefixes of the game's own libraries, on top of [GAME_ASSEMBLIES]
+const GAME_ASSEMBLY_PREFIXES: [&str; 2] = ["Owlcat.", "Kingmaker."];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// The archive entry the issue is in
+    pub file: String,
+    pub pointer: JsonPointer,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// Another node of the same file already has this `$id`
+    DuplicateId(Id),
+    /// No node of the file has the `$id` the `$ref` points to
+    DanglingReference(Id),
+    /// Following the `$ref` leads back to it
+    ReferenceCycle(Id),
+    /// The edits removed the last `$ref` to this `$id`, which the game shared
+    Orphan(Id),
+    /// A node whose `$type` isn't defined by the game
+    UnknownType(String),
+    /// A value the editor's readers don't expect
+    Unreadable(JsonError),
+}
+
+impl Problem {
+    /// Whether the game is likely to fail loading the save. Unknown types
+    /// are only a problem once the mod defining them is gone.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Problem::UnknownType(_))
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at '{}': {}", self.file, self.pointer, self.problem)
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::DuplicateId(id) => write!(f, "duplicate $id '{}'", id),
+            Problem::DanglingReference(id) => write!(f, "$ref '{}' points to no $id", id),
+            Problem::ReferenceCycle(id) => write!(f, "$ref '{}' is part of a cycle", id),
+            Problem::Orphan(id) => write!(f, "nothing references the $id '{}' anymore", id),
+            Problem::UnknownType(t) => write!(f, "value of unknown type '{}'", t),
+            Problem::Unreadable(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Check all the JSON entries of the save at `path`
+pub async fn check_integrity(path: &Path) -> Result<Vec<Issue>, SaveError> {
+    let (mut archive, _) = super::load_archive(path).await?;
+    let (header, _) = super::extract_header(&mut archive)?;
+    let format = SaveFormat::of(&header);
+
+    let names = super::json_entry_names(&archive);
+
+    let mut issues = vec![];
+    for name in names {
+        match super::extract_json(&mut archive, &name) {
+            Ok(index) => issues.extend(check_document(&name, &index, &format)),
+            Err(SaveError::Json(file, error)) => issues.push(Issue {
+                file,
+                pointer: JsonPointer::root(),
+                problem: Problem::Unreadable(error),
+            }),
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(issues)
+}
+
+/// The issues of the given entries, named as in the archive
+pub(super) fn check_documents(
+    documents: &[(&str, &IndexedJson)],
+    format: &SaveFormat,
+) -> Vec<Issue> {
+    documents
+        .iter()
+        .flat_map(|(file, index)| check_document(file, index, format))
+        .collect()
+}
+
+/// The nodes of `index` which were referenced (their `$id` being in
+/// `referenced_before`) before it was edited, but aren't anymore
+pub(super) fn orphans(file: &str, referenced_before: &[Id], index: &IndexedJson) -> Vec<Issue> {
+    referenced_before
+        .iter()
+        .filter(|id| index.referenced_by(id).is_empty())
+        .filter_map(|id| {
+            Some(Issue {
+                file: file.to_string(),
+                pointer: index.pointer_for(id.clone()).ok()?,
+                problem: Problem::Orphan(id.clone()),
+            })
+        })
+        .collect()
+}
+
+/// The issues of a single entry, `file` being its name in the archive
+fn check_document(file: &str, index: &IndexedJson, format: &SaveFormat) -> Vec<Issue> {
+    let mut problems = vec![];
+
+    let mut references = References::default();
+    references.collect(&index.json, &mut JsonPointer::root(), &mut problems);
+    references.resolve(index, &mut problems);
+
+    let diagnostics = match file {
+        "header.json" => data::read_header(index).map(|h| h.diagnostics),
+        "party.json" => data::read_party(index, format).map(|p| p.diagnostics),
+        "player.json" => data::read_player(index).map(|p| p.diagnostics),
+        _ => Ok(vec![]),
+    };
+    match diagnostics {
+        Ok(diagnostics) => problems.extend(
+            diagnostics
+                .into_iter()
+                .map(|d: Diagnostic| (d.pointer, Problem::Unreadable(d.error))),
+        ),
+        Err(error) => problems.push((JsonPointer::root(), Problem::Unreadable(error))),
+    }
+
+    problems
+        .into_iter()
+        .map(|(pointer, problem)| Issue {
+            file: file.to_string(),
+            pointer,
+            problem,
+        })
+        .collect()
+}
+
+/// The `$id`s of a document, the first node having it when it's duplicated
+#[derive(Default)]
+struct References {
+    ids: BTreeMap<Id, JsonPointer>,
+}
+
+impl References {
+    fn collect(
+        &mut self,
+        json: &Value,
+        path: &mut JsonPointer,
+        problems: &mut Vec<(JsonPointer, Problem)>,
+    ) {
+        match json {
+            Value::Array(values) => {
+                for (idx, value) in values.iter().enumerate() {
+                    path.push(idx);
+                    self.collect(value, path, problems);
+                    path.pop();
+                }
+            }
+            Value::Object(map) => {
+                match map.get("$type") {
+                    Some(Value::String(t)) if is_game_type(t) => (),
+                    Some(Value::String(t)) => {
+                        problems.push((path.clone(), Problem::UnknownType(t.clone())))
+                    }
+                    Some(other) => {
+                        problems.push((path.clone(), Problem::UnknownType(other.to_string())))
+                    }
+                    None => (),
+                }
+
+                if let Some(id) = map.get("$id").and_then(|j| j.as_str()) {
+                    let id = Id::from(id);
+
+                    match self.ids.contains_key(&id) {
+                        true => problems.push((path.clone(), Problem::DuplicateId(id))),
+                        false => {
+                            self.ids.insert(id, path.clone());
+                        }
+                    }
+                }
+
+                for (key, value) in map {
+                    path.push(key);
+                    self.collect(value, path, problems);
+                    path.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Check the `$ref`s of the document, as tracked by its index
+    fn resolve(&self, index: &IndexedJson, problems: &mut Vec<(JsonPointer, Problem)>) {
+        for (reference, pointers) in index.references() {
+            if !self.ids.contains_key(reference) {
+                problems.extend(
+                    pointers
+                        .iter()
+                        .map(|p| (p.clone(), Problem::DanglingReference(reference.clone()))),
+                );
+                continue;
+            }
+
+            // The referenced node may itself be a reference, follow them until
+            // a plain node. Dangling ones along the way are reported on their own.
+            let mut visited = vec![reference];
+            while let Some(next) = self.target(&index.json, visited[visited.len() - 1]) {
+                if visited.contains(&next) {
+                    problems.extend(
+                        pointers
+                            .iter()
+                            .map(|p| (p.clone(), Problem::ReferenceCycle(reference.clone()))),
+                    );
+                    break;
+                }
+                visited.push(next);
+            }
+        }
+    }
+
+    /// The `$ref` of the node having the `reference` id, if it has one
+    fn target(&self, json: &Value, reference: &Id) -> Option<&Id> {
+        let pointer = self.ids.get(reference)?;
+        let next = pointer.get(json)?.get("$ref")?.as_str()?;
+
+        self.ids.get_key_value(&Id::from(next)).map(|(id, _)| id)
+    }
+}
+
+/// `$type`s are written as `<type name>, <assembly>`
+fn is_game_type(t: &str) -> bool {
+    match t.rsplit_once(", ") {
+        Some((_, assembly)) => {
+            GAME_ASSEMBLIES.contains(&assembly)
+                || GAME_ASSEMBLY_PREFIXES
+                    .iter()
+                    .any(|prefix| assembly.starts_with(prefix))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::JsonPatch;
+
+    fn check(file: &str, json: &str) -> Vec<String> {
+        let index = IndexedJson::new(serde_json::from_str(json).unwrap());
+
+        check_document(file, &index, &SaveFormat::Known(2))
+            .iter()
+            .map(Issue::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn finds_broken_references() {
+        let issues = check(
+            "Area.json",
+            r#"{"$id":"1","a":{"$id":"2"},"b":{"$id":"2"},"c":{"$ref":"3"},"d":{"$ref":"1"},
+            "e":{"$id":"4","$ref":"5"},"f":{"$id":"5","$ref":"4"}}"#,
+        );
+
+        assert_eq!(
+            issues,
+            vec![
+                "Area.json at '/b': duplicate $id '2'",
+                "Area.json at '/c': $ref '3' points to no $id",
+                "Area.json at '/f': $ref '4' is part of a cycle",
+                "Area.json at '/e': $ref '5' is part of a cycle",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_values_of_unknown_types() {
+        let issues = check(
+            "Area.json",
+            r#"{"$id":"1","m_EntityData":[
+            {"$id":"2","$type":"Kingmaker.EntitySystem.Entities.MapObjectEntityData, Assembly-CSharp",
+            "Facts":{"$id":"4","$type":"System.Collections.Generic.List`1[[Kingmaker.Fact, Assembly-CSharp]], mscorlib",
+            "Buff":{"$id":"5","$type":"SomeMod.Buffs.Haste, SomeMod"}}},
+            {"$id":"3","$type":"SomeMod.Entities.Portal, SomeMod"}],"Broken":{"$type":7}}"#,
+        );
+
+        assert_eq!(
+            issues,
+            vec![
+                "Area.json at '/m_EntityData/0/Facts/Buff': value of unknown type 'SomeMod.Buffs.Haste, SomeMod'",
+                "Area.json at '/m_EntityData/1': value of unknown type 'SomeMod.Entities.Portal, SomeMod'",
+                "Area.json at '/Broken': value of unknown type '7'",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_values_the_edits_orphaned() {
+        let json =
+            r#"{"$id":"1","a":{"$id":"2"},"b":{"$ref":"2"},"c":{"$id":"3"},"d":{"$ref":"3"}}"#;
+        let mut index = IndexedJson::new(serde_json::from_str(json).unwrap());
+        let referenced: Vec<_> = index.references().map(|(id, _)| id.clone()).collect();
+
+        // `3` leaves along with its reference
+        for pointer in ["/b", "/d", "/c"] {
+            index.patch(&JsonPatch::u64(pointer.into(), 0)).unwrap();
+        }
+        let issues: Vec<_> = orphans("party.json", &referenced, &index)
+            .iter()
+            .map(Issue::to_string)
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec!["party.json at '/a': nothing references the $id '2' anymore"]
+        );
+    }
+
+    #[test]
+    fn finds_values_the_readers_dont_expect() {
+        let issues = check(
+            "player.json",
+            r#"{"$id":"1","Money":"a lot","m_GlobalMaps":[]}"#,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("player.json at '': "));
+    }
+}
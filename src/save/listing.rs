@@ -0,0 +1,248 @@
+//! Discovery of the save games present in a directory.
+
+use super::SaveError;
+use crate::data::Header;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveSummary {
+    pub path: PathBuf,
+    pub header: Header,
+    /// The in-game screenshot (PNG) stored alongside the header
+    pub thumbnail: Option<Vec<u8>>,
+    /// Last modification of the archive on disk
+    pub modified: Option<SystemTime>,
+}
+
+impl SaveSummary {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// The saves of a single playthrough, identified by the header's `GameId`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Campaign<'a> {
+    pub game_id: Option<&'a str>,
+    pub dlc_campaign: Option<&'a str>,
+    /// Never empty, and sorted from the newest to the oldest save
+    pub saves: Vec<&'a SaveSummary>,
+}
+
+impl<'a> Campaign<'a> {
+    pub fn newest(&self) -> &'a SaveSummary {
+        self.saves[0]
+    }
+
+    /// A human friendly name for the campaign, based on its latest save
+    pub fn title(&self) -> String {
+        let character = self
+            .newest()
+            .header
+            .player_character_name
+            .clone()
+            .unwrap_or_else(|| "Unknown character".to_string());
+
+        match self.dlc_campaign {
+            Some(_) => format!("{} (DLC campaign)", character),
+            None => character,
+        }
+    }
+}
+
+/// Group `saves` by campaign. Campaigns are ordered by their most recent
+/// save, newest first. Saves without a `GameId` are grouped together.
+pub fn group_by_campaign<'a, I>(saves: I) -> Vec<Campaign<'a>>
+where
+    I: IntoIterator<Item = &'a SaveSummary>,
+{
+    let mut groups: BTreeMap<Option<&str>, Vec<&SaveSummary>> = BTreeMap::new();
+
+    for save in saves {
+        groups
+            .entry(save.header.game_id.as_deref())
+            .or_default()
+            .push(save);
+    }
+
+    let mut campaigns: Vec<_> = groups
+        .into_iter()
+        .map(|(game_id, mut saves)| {
+            saves.sort_by_key(|s| std::cmp::Reverse(s.modified));
+
+            Campaign {
+                game_id,
+                dlc_campaign: saves[0].header.dlc_campaign.as_deref(),
+                saves,
+            }
+        })
+        .collect();
+
+    campaigns.sort_by_key(|c| std::cmp::Reverse(c.newest().modified));
+
+    campaigns
+}
+
+/// Read the header of the save game at `path`. Only the `header.json` entry is
+/// decompressed, which makes it much cheaper than a full load.
+pub async fn read_header(path: &Path) -> Result<Header, SaveError> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)?;
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))?;
+        let (header, _) = super::extract_header(&mut archive)?;
+
+        Ok(header)
+    })
+    .await
+    .map_err(|err| SaveError::Io(format!("{}", err)))?
+}
+
+/// Read what is needed to present the save game at `path`: its header and
+/// thumbnail. Like [read_header], the rest of the archive is left untouched.
+pub async fn read_summary(path: &Path) -> Result<SaveSummary, SaveError> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)?;
+        let modified = file.metadata().and_then(|m| m.modified()).ok();
+        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))?;
+        let (header, _) = super::extract_header(&mut archive)?;
+        let thumbnail = super::extract_thumbnail(&mut archive)?;
+
+        Ok(SaveSummary {
+            path,
+            header,
+            thumbnail,
+            modified,
+        })
+    })
+    .await
+    .map_err(|err| SaveError::Io(format!("{}", err)))?
+}
+
+/// List the save games (`.zks` files) found in `directory` along with their
+/// header and thumbnail. Files which cannot be read are skipped.
+pub async fn list_saves(directory: &Path) -> Result<Vec<SaveSummary>, SaveError> {
+    let mut entries = tokio::fs::read_dir(directory).await?;
+    let mut saves = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+
+        if path.extension().is_none_or(|ext| ext != "zks") {
+            continue;
+        }
+
+        match read_summary(&path).await {
+            Ok(summary) => saves.push(summary),
+            Err(err) => log::warn!("Skipping unreadable save {:?}: {:?}", path, err),
+        }
+    }
+
+    Ok(saves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::SaveType;
+    use std::io::Write;
+    use std::time::Duration;
+
+    fn write_save(path: &Path, header: &str, thumbnail: Option<&[u8]>) {
+        let file = std::fs::File::create(path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+
+        zip.start_file("header.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(header.as_bytes()).unwrap();
+
+        if let Some(png) = thumbnail {
+            zip.start_file("header.png", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(png).unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_saves_reads_headers_and_skips_other_files() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("list_saves")
+            .tempdir()
+            .unwrap();
+
+        write_save(
+            &tmp_dir.path().join("Manual_1.zks"),
+            r#"{"Name":"Drezen","CompatibilityVersion":2,"Type":"Manual","PlayerCharacterName":"Yaslin",
+                "PartyPortraits":[
+                    {"$id":"2","m_Blueprint":null,"m_Data":{"$id":"3","m_CustomPortraitId":"AA-F109"}},
+                    {"$id":"4","m_Blueprint":"b9fea9f24838d43469948aa54088d600","m_Data":null}
+                ]}"#,
+            Some(b"\x89PNG"),
+        );
+        write_save(&tmp_dir.path().join("Broken.zks"), "not json", None);
+        std::fs::write(tmp_dir.path().join("notes.txt"), "hello").unwrap();
+
+        let saves = list_saves(tmp_dir.path()).await.unwrap();
+
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].file_name(), "Manual_1.zks");
+        assert_eq!(saves[0].header.name, "Drezen");
+        assert_eq!(saves[0].header.save_type, Some(SaveType::Manual));
+        assert_eq!(
+            saves[0].header.player_character_name,
+            Some("Yaslin".to_string())
+        );
+        assert_eq!(saves[0].thumbnail, Some(b"\x89PNG".to_vec()));
+
+        let portraits = &saves[0].header.party_portraits;
+        assert_eq!(portraits.len(), 2);
+        assert_eq!(portraits[0].custom_portrait_id(), Some("AA-F109"));
+        assert_eq!(portraits[1].custom_portrait_id(), None);
+    }
+
+    fn summary(name: &str, game_id: Option<&str>, age: u64) -> SaveSummary {
+        SaveSummary {
+            path: PathBuf::from(format!("{}.zks", name)),
+            header: Header {
+                name: name.to_string(),
+                game_id: game_id.map(str::to_string),
+                ..Default::default()
+            },
+            thumbnail: None,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1000 - age)),
+        }
+    }
+
+    #[test]
+    fn group_by_campaign_orders_by_newest_save() {
+        let saves = vec![
+            summary("a-old", Some("a"), 50),
+            summary("b-new", Some("b"), 1),
+            summary("a-new", Some("a"), 10),
+            summary("unknown", None, 100),
+        ];
+
+        let campaigns = group_by_campaign(&saves);
+        let names: Vec<Vec<&str>> = campaigns
+            .iter()
+            .map(|c| c.saves.iter().map(|s| s.header.name.as_str()).collect())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![vec!["b-new"], vec!["a-new", "a-old"], vec!["unknown"]]
+        );
+        assert_eq!(campaigns[1].game_id, Some("a"));
+        assert_eq!(campaigns[1].newest().header.name, "a-new");
+    }
+}
@@ -1,17 +1,17 @@
-use super::SaveError;
-use crate::data::{Header, Party, Player};
+use super::{Fingerprint, SaveError};
+use crate::data::{Diagnostic, Header, Party, Player, SaveFormat};
+use crate::json::IndexedJson;
 use async_channel::{Receiver, Sender};
-use iced::advanced::subscription::{EventStream, Hasher, Recipe};
-use std::hash::Hash;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LoadingStep {
     Initialized,
     ReadingFile,
+    ReadingHeader,
     ReadingParty,
     ReadingPlayer,
-    ReadingHeader,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -20,6 +20,35 @@ pub struct LoadingDone {
     pub party: Party,
     pub player: Player,
     pub archive_path: PathBuf,
+    pub documents: SaveDocuments,
+}
+
+impl LoadingDone {
+    /// What couldn't be read in the save, and has been left out of the model
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.header
+            .diagnostics
+            .iter()
+            .chain(&self.party.diagnostics)
+            .chain(&self.player.diagnostics)
+    }
+
+    pub fn format(&self) -> SaveFormat {
+        SaveFormat::of(&self.header)
+    }
+}
+
+/// The JSON documents of a loaded save, kept to be patched when saving
+/// instead of parsing the archive again. Clones share the documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveDocuments {
+    pub header: Arc<IndexedJson>,
+    pub party: Arc<IndexedJson>,
+    pub player: Arc<IndexedJson>,
+    /// The save the documents were read from, as it was on disk
+    pub fingerprint: Fingerprint,
+    /// The content of that save, to read its other entries from the same state
+    pub archive: Arc<[u8]>,
 }
 
 impl LoadingStep {
@@ -27,9 +56,9 @@ impl LoadingStep {
         match self {
             LoadingStep::Initialized => 0.0,
             LoadingStep::ReadingFile => 1.0,
-            LoadingStep::ReadingParty => 2.0,
-            LoadingStep::ReadingPlayer => 3.0,
-            LoadingStep::ReadingHeader => 5.0,
+            LoadingStep::ReadingHeader => 2.0,
+            LoadingStep::ReadingParty => 3.0,
+            LoadingStep::ReadingPlayer => 4.0,
         }
     }
 
@@ -41,9 +70,9 @@ impl LoadingStep {
         match self {
             LoadingStep::Initialized => "Initialized".to_string(),
             LoadingStep::ReadingFile => "Reading file from disk".to_string(),
+            LoadingStep::ReadingHeader => "Parsing the save information".to_string(),
             LoadingStep::ReadingParty => "Parsing the party information".to_string(),
             LoadingStep::ReadingPlayer => "Parsing the player information".to_string(),
-            LoadingStep::ReadingHeader => "Parsing the save information".to_string(),
         }
     }
 }
@@ -62,42 +91,56 @@ impl SaveLoader {
     }
 
     pub async fn load(self) -> Result<LoadingDone, SaveError> {
-        self.tx.send(LoadingStep::ReadingFile).await?;
-        let mut archive = super::load_archive(&self.file_path).await?;
-
-        self.tx.send(LoadingStep::ReadingParty).await?;
-        let (party, _) = super::extract_party(&mut archive).await?;
+        self.notify(LoadingStep::ReadingFile);
+        let (mut archive, fingerprint) = super::load_archive(&self.file_path).await?;
+
+        // First, as the other files are read according to the save's format
+        self.notify(LoadingStep::ReadingHeader);
+        let (header, header_index) = super::extract_header(&mut archive)?;
+        let format = SaveFormat::of(&header);
+        if let Some(warning) = format.warning() {
+            log::warn!("{}: {}", self.file_path.display(), warning);
+        }
 
-        self.tx.send(LoadingStep::ReadingPlayer).await?;
-        let (player, _) = super::extract_player(&mut archive).await?;
+        self.notify(LoadingStep::ReadingParty);
+        let (party, party_index) = super::extract_party(&mut archive, &format)?;
 
-        self.tx.send(LoadingStep::ReadingHeader).await?;
-        let (header, _) = super::extract_header(&mut archive).await?;
+        self.notify(LoadingStep::ReadingPlayer);
+        let (player, player_index) = super::extract_player(&mut archive)?;
 
         Ok(LoadingDone {
             party,
             player,
             header,
             archive_path: self.file_path,
+            documents: SaveDocuments {
+                header: Arc::new(header_index),
+                party: Arc::new(party_index),
+                player: Arc::new(player_index),
+                fingerprint,
+                archive: super::archive_bytes(&archive),
+            },
         })
     }
+
+    // Only the latest step is of interest, and nobody listening isn't an error
+    fn notify(&self, step: LoadingStep) {
+        let _ = self.tx.force_send(step);
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct LoadNotifications(Receiver<LoadingStep>);
 
-// Make sure iced can use our download stream
-impl Recipe for LoadNotifications {
-    type Output = LoadingStep;
-
-    fn hash(&self, state: &mut Hasher) {
-        std::any::TypeId::of::<Self>().hash(state);
+impl LoadNotifications {
+    /// Wait for the next step of the loading process. Returns `None`
+    /// once the loader is done (or has been dropped).
+    pub async fn next(&self) -> Option<LoadingStep> {
+        self.0.recv().await.ok()
     }
 
-    fn stream(
-        self: Box<Self>,
-        _input: EventStream,
-    ) -> futures::stream::BoxStream<'static, Self::Output> {
-        Box::pin(self.0)
+    /// A stream of the loading steps, ending once the loader is done.
+    pub fn into_stream(self) -> impl futures::Stream<Item = LoadingStep> {
+        self.0
     }
 }
@@ -1,32 +1,128 @@
-use crate::data::{self, Header, Party, Player};
-use crate::json::{IndexedJson, JsonError};
-use async_channel::SendError;
-use std::path::Path;
+//! Loading and saving of the `.zks` archives.
+//!
+//! Both [SaveLoader] and [SavingSaveGame] report their progress through a
+//! notification stream. Those are purely informative: the stream only keeps
+//! the latest step and can be dropped without affecting the operation.
 
+use crate::data::{self, Header, Party, Player, SaveFormat};
+use crate::json::{IndexedJson, JsonError, PatchError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod backup;
+mod compression;
+mod fingerprint;
+mod integrity;
+mod listing;
 mod loading;
+mod naming;
+mod patch_set;
+mod paths;
 mod saving;
 
-pub use loading::{LoadNotifications, LoadingDone, LoadingStep, SaveLoader};
-pub use saving::{SaveNotifications, SavingSaveGame, SavingStep};
-// use save::{SaveLoader, SaveError, LoadingStep};
+pub use backup::{default_backup_dir, list_backups, restore_backup, Backup, BackupPolicy};
+pub use compression::Compression;
+pub use fingerprint::Fingerprint;
+pub use integrity::{check_integrity, Issue, Problem};
+pub use listing::{
+    group_by_campaign, list_saves, read_header, read_summary, Campaign, SaveSummary,
+};
+pub use loading::{LoadNotifications, LoadingDone, LoadingStep, SaveDocuments, SaveLoader};
+pub use naming::{NamingTemplate, SaveDestination};
+pub use patch_set::PatchSet;
+pub use paths::{custom_portrait, default_save_game_dir};
+pub use saving::{SaveNotifications, SavingDone, SavingSaveGame, SavingStep};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SaveError {
     Io(String),
-    Serde(String, String),
-    Json(String, String),
+    /// An entry of the archive couldn't be parsed, read into the model or patched
+    Json(String, JsonError), // (file name, error)
     Zip(String),
-    SavingNotifications(SendError<SavingStep>),
-    LoadingNotifications(SendError<LoadingStep>),
+    /// A file of the archive couldn't be read or written
+    Archive(String, String), // (file name, error)
+    /// Some edits of a file couldn't be applied, so none of them were
+    Patches(String, Vec<PatchError>), // (file name, failed patches)
+    InvalidTemplate(String),
+    /// A file of edits (see [PatchSet]) couldn't be read or written
+    InvalidPatchSet(PathBuf, String), // (path, error)
+    /// The save changed on disk since it was loaded
+    ModifiedOnDisk(PathBuf),
+    /// The edits would introduce these issues in the save, which hasn't been written
+    Integrity(Vec<Issue>),
 }
 
 impl SaveError {
-    fn serde_error(file_name: &str, err: serde_json::Error) -> SaveError {
-        SaveError::Serde(file_name.to_string(), format!("{}", err))
+    /// The error followed by its causes, eg. `invalid party.json in the save:
+    /// expected an array at '/m_EntityData', found object`
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = std::error::Error::source(self);
+
+        while let Some(cause) = source {
+            report.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+
+        report
+    }
+
+    fn json_error(file_name: &str, err: impl Into<JsonError>) -> SaveError {
+        SaveError::Json(file_name.to_string(), err.into())
     }
 
-    fn json_error(file_name: &str, err: JsonError) -> SaveError {
-        SaveError::Json(file_name.to_string(), format!("{:?}", err))
+    fn archive_error(file_name: &str, err: impl std::fmt::Display) -> SaveError {
+        SaveError::Archive(file_name.to_string(), format!("{}", err))
+    }
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "file system error: {}", err),
+            SaveError::Json(file, _) => write!(f, "invalid {} in the save", file),
+            SaveError::Zip(err) => write!(f, "invalid save archive: {}", err),
+            SaveError::Archive(file, err) => {
+                write!(f, "{} couldn't be read or written: {}", file, err)
+            }
+            SaveError::Patches(file, errors) => {
+                write!(
+                    f,
+                    "{} edit(s) couldn't be applied to {}",
+                    errors.len(),
+                    file
+                )
+            }
+            SaveError::InvalidTemplate(err) => write!(f, "invalid naming template: {}", err),
+            SaveError::InvalidPatchSet(path, err) => {
+                write!(f, "invalid edits file {}: {}", path.display(), err)
+            }
+            SaveError::ModifiedOnDisk(path) => {
+                write!(f, "{} changed on disk since it was loaded", path.display())
+            }
+            SaveError::Integrity(issues) => {
+                write!(f, "the edits would corrupt the save")?;
+                if let Some(issue) = issues.first() {
+                    write!(f, ", {}", issue)?;
+                }
+                match issues.len() {
+                    0 | 1 => Ok(()),
+                    n => write!(f, " (and {} more)", n - 1),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Json(_, err) => Some(err),
+            // The first one is enough to tell what went wrong
+            SaveError::Patches(_, errors) => errors.first().map(|e| e as _),
+            _ => None,
+        }
     }
 }
 
@@ -42,43 +138,74 @@ impl From<zip::result::ZipError> for SaveError {
     }
 }
 
-impl From<SendError<SavingStep>> for SaveError {
-    fn from(e: SendError<SavingStep>) -> Self {
-        Self::SavingNotifications(e)
-    }
+/// The names of the JSON entries of the loaded save, sorted
+pub fn json_entries(documents: &SaveDocuments) -> Result<Vec<String>, SaveError> {
+    let archive = open_archive(documents.archive.clone())?;
+
+    let mut names = json_entry_names(&archive);
+    names.sort();
+
+    Ok(names)
 }
 
-impl From<SendError<LoadingStep>> for SaveError {
-    fn from(e: SendError<LoadingStep>) -> Self {
-        Self::LoadingNotifications(e)
-    }
+/// Read any JSON entry of the loaded save, eg. the state of an area, as it
+/// was when the documents were read rather than as it is now on disk
+pub fn read_json_entry(documents: &SaveDocuments, name: &str) -> Result<IndexedJson, SaveError> {
+    let mut archive = open_archive(documents.archive.clone())?;
+
+    extract_json(&mut archive, name)
 }
 
 // Function commons to loading and saving
 
-type InMemoryArchive = zip::ZipArchive<std::io::Cursor<std::vec::Vec<u8>>>;
+// The bytes are shared so that they can also be read directly, see `archive_bytes`
+type InMemoryArchive = zip::ZipArchive<std::io::Cursor<Arc<[u8]>>>;
 
-async fn extract_party(archive: &mut InMemoryArchive) -> Result<(Party, IndexedJson), SaveError> {
-    let file = archive.by_name("party.json")?;
+/// The content of the archive file, as read from disk
+fn archive_bytes(archive: &InMemoryArchive) -> Arc<[u8]> {
+    // Cloning the archive only clones the reader, not its bytes
+    archive.clone().into_inner().into_inner()
+}
 
-    let json =
-        serde_json::from_reader(file).map_err(|err| SaveError::serde_error("party.json", err))?;
+fn open_archive(bytes: Arc<[u8]>) -> Result<InMemoryArchive, SaveError> {
+    Ok(zip::ZipArchive::new(std::io::Cursor::new(bytes))?)
+}
 
-    let indexed_json = IndexedJson::new(json);
+fn json_entry_names(archive: &InMemoryArchive) -> Vec<String> {
+    archive
+        .file_names()
+        .filter(|name| name.ends_with(".json"))
+        .map(str::to_string)
+        .collect()
+}
 
-    let party =
-        data::read_party(&indexed_json).map_err(|err| SaveError::json_error("party.json", err))?;
+fn extract_json<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<IndexedJson, SaveError> {
+    let file = archive.by_name(name)?;
 
-    Ok((party, indexed_json))
+    let json = serde_json::from_reader(file).map_err(|err| SaveError::json_error(name, err))?;
+
+    Ok(IndexedJson::new(json))
 }
 
-async fn extract_player(archive: &mut InMemoryArchive) -> Result<(Player, IndexedJson), SaveError> {
-    let file = archive.by_name("player.json")?;
+fn extract_party<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    format: &SaveFormat,
+) -> Result<(Party, IndexedJson), SaveError> {
+    let indexed_json = extract_json(archive, "party.json")?;
+
+    let party = data::read_party(&indexed_json, format)
+        .map_err(|err| SaveError::json_error("party.json", err))?;
 
-    let json =
-        serde_json::from_reader(file).map_err(|err| SaveError::serde_error("player.json", err))?;
+    Ok((party, indexed_json))
+}
 
-    let indexed_json = IndexedJson::new(json);
+fn extract_player<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<(Player, IndexedJson), SaveError> {
+    let indexed_json = extract_json(archive, "player.json")?;
 
     let player = data::read_player(&indexed_json)
         .map_err(|err| SaveError::json_error("player.json", err))?;
@@ -86,13 +213,10 @@ async fn extract_player(archive: &mut InMemoryArchive) -> Result<(Player, Indexe
     Ok((player, indexed_json))
 }
 
-async fn extract_header(archive: &mut InMemoryArchive) -> Result<(Header, IndexedJson), SaveError> {
-    let file = archive.by_name("header.json")?;
-
-    let json =
-        serde_json::from_reader(file).map_err(|err| SaveError::serde_error("header.json", err))?;
-
-    let indexed_json = IndexedJson::new(json);
+fn extract_header<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<(Header, IndexedJson), SaveError> {
+    let indexed_json = extract_json(archive, "header.json")?;
 
     let header = data::read_header(&indexed_json)
         .map_err(|err| SaveError::json_error("header.json", err))?;
@@ -100,10 +224,62 @@ async fn extract_header(archive: &mut InMemoryArchive) -> Result<(Header, Indexe
     Ok((header, indexed_json))
 }
 
-async fn load_archive(path: &Path) -> Result<InMemoryArchive, SaveError> {
+/// The screenshot taken by the game when saving, as PNG. Not every save has one.
+fn extract_thumbnail<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<Option<Vec<u8>>, SaveError> {
+    let mut file = match archive.by_name("header.png") {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut buf = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut buf)?;
+
+    Ok(Some(buf))
+}
+
+/// Write `content` to a temporary file next to `path`, then move it in place.
+/// A crash midway leaves the previous file (if any) untouched.
+async fn write_atomically(path: &Path, content: Vec<u8>) -> Result<(), SaveError> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let directory = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+
+        let mut file = tempfile::NamedTempFile::new_in(directory)?;
+        file.write_all(&content)?;
+        // The temporary file is only readable by its owner, unlike what it replaces
+        match std::fs::metadata(&path) {
+            Ok(metadata) => file.as_file().set_permissions(metadata.permissions())?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into()),
+        }
+        file.as_file().sync_all()?;
+        file.persist(&path).map_err(|err| err.error)?;
+
+        // Make the rename itself durable. Not every file system supports it,
+        // and the content is already safe at this point.
+        #[cfg(unix)]
+        if let Err(err) = std::fs::File::open(directory).and_then(|d| d.sync_all()) {
+            log::debug!("Couldn't sync directory {:?}: {}", directory, err);
+        }
+
+        Ok(())
+    })
+    .await
+    .map_err(|err| SaveError::Io(format!("{}", err)))?
+}
+
+async fn load_archive(path: &Path) -> Result<(InMemoryArchive, Fingerprint), SaveError> {
+    let modified = tokio::fs::metadata(path).await?.modified().ok();
     let buf = tokio::fs::read(path).await?;
-    let reader = std::io::Cursor::new(buf);
-    let archive = zip::ZipArchive::new(reader)?;
+    let fingerprint = Fingerprint::new(&buf, modified);
+    let archive = open_archive(Arc::from(buf))?;
 
     // verify archive contains required files ahead of time
     let exists = |s: &str| {
@@ -117,5 +293,5 @@ async fn load_archive(path: &Path) -> Result<InMemoryArchive, SaveError> {
     exists("party.json")?;
     exists("player.json")?;
 
-    Ok(archive)
+    Ok((archive, fingerprint))
 }
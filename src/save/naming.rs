@@ -0,0 +1,357 @@
+//! Where an edited save is written, and how its copies are named.
+
+use super::SaveError;
+use crate::data::Header;
+use std::path::{Path, PathBuf};
+
+/// Characters Windows refuses in file names, along with the control characters
+const INVALID_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveDestination {
+    /// A new file next to the original save, named after the template
+    Copy(NamingTemplate),
+    /// An explicit path, replaced if it already exists
+    Path(PathBuf),
+    /// Replace the original save
+    Overwrite,
+}
+
+impl Default for SaveDestination {
+    fn default() -> Self {
+        SaveDestination::Copy(NamingTemplate::default())
+    }
+}
+
+impl SaveDestination {
+    /// The path to write to, along with the in-game name of the save when
+    /// it has to change. Only copies are renamed, so that they can be told
+    /// apart from the original in the game's load menu.
+    pub(super) async fn resolve(
+        &self,
+        archive_path: &Path,
+        header: &Header,
+    ) -> Result<(Option<String>, PathBuf), SaveError> {
+        match self {
+            SaveDestination::Copy(template) => {
+                let (name, path) = template.find_free_name(archive_path, header).await?;
+                Ok((Some(name), path))
+            }
+            SaveDestination::Path(path) => Ok((None, path.clone())),
+            SaveDestination::Overwrite => Ok((None, archive_path.to_path_buf())),
+        }
+    }
+}
+
+/// Naming of the copies, for both the file and the save name shown in game.
+///
+/// Templates can use the following placeholders:
+/// - `{stem}`: the original file name, without its extension
+/// - `{name}`: the original save name
+/// - `{date}`: today's date, as `YYYY-MM-DD`
+/// - `{n}`: a counter, increased until the file name is free. It is empty
+///   for the first copy, then starts at 2.
+///
+/// The `.zks` extension is added to the file name when missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingTemplate {
+    pub file_name: String,
+    pub save_name: String,
+}
+
+impl Default for NamingTemplate {
+    fn default() -> Self {
+        NamingTemplate {
+            file_name: NamingTemplate::DEFAULT_FILE_NAME.to_string(),
+            save_name: NamingTemplate::DEFAULT_SAVE_NAME.to_string(),
+        }
+    }
+}
+
+impl NamingTemplate {
+    pub const DEFAULT_FILE_NAME: &'static str = "{stem}__Copy{n}";
+    pub const DEFAULT_SAVE_NAME: &'static str = "{name} - Edited {n}";
+
+    /// The names of the first copy, without checking what exists on disk.
+    pub fn preview(
+        &self,
+        archive_path: &Path,
+        header: &Header,
+    ) -> Result<(String, String), SaveError> {
+        let values = Values::new(archive_path, header);
+
+        Ok((
+            self.render_save_name(&values, 1)?,
+            self.render_file_name(&values, 1)?,
+        ))
+    }
+
+    async fn find_free_name(
+        &self,
+        archive_path: &Path,
+        header: &Header,
+    ) -> Result<(String, PathBuf), SaveError> {
+        let values = Values::new(archive_path, header);
+
+        for counter in 1.. {
+            let path = archive_path.with_file_name(self.render_file_name(&values, counter)?);
+
+            match tokio::fs::metadata(&path).await {
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok((self.render_save_name(&values, counter)?, path));
+                }
+                Err(err) => return Err(err.into()),
+                Ok(_) => (),
+            }
+
+            // Without a counter, trying again would only give the same name
+            if !self.file_name.contains("{n}") {
+                return Err(SaveError::InvalidTemplate(format!(
+                    "{} already exists, use {{n}} in the file name template to number the copies",
+                    path.display()
+                )));
+            }
+        }
+
+        unreachable!("the counter ran out of numbers")
+    }
+
+    /// The placeholders' values are made valid file names, but the template itself
+    /// has to be one
+    fn render_file_name(&self, values: &Values, counter: u32) -> Result<String, SaveError> {
+        let rendered = render(&self.file_name, &values.file_name_safe(), counter)?;
+        // Windows drops them, which could make the names of two copies clash
+        let mut file_name = rendered.trim_end_matches(['.', ' ']).to_string();
+
+        if file_name.trim().is_empty() || file_name.contains(is_invalid_in_file_name) {
+            return Err(SaveError::InvalidTemplate(format!(
+                "'{}' isn't a valid file name",
+                file_name
+            )));
+        }
+
+        if !file_name.ends_with(".zks") {
+            file_name.push_str(".zks");
+        }
+
+        Ok(file_name)
+    }
+
+    fn render_save_name(&self, values: &Values, counter: u32) -> Result<String, SaveError> {
+        // Trimming lets the default template avoid a trailing space on the first copy
+        Ok(render(&self.save_name, values, counter)?.trim().to_string())
+    }
+}
+
+struct Values {
+    stem: String,
+    name: String,
+    date: String,
+}
+
+impl Values {
+    fn new(archive_path: &Path, header: &Header) -> Values {
+        Values {
+            stem: archive_path
+                .file_stem()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Unknown Save Name".to_string()),
+            name: header.name.clone(),
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// The values with the characters not allowed in file names replaced by `_`
+    fn file_name_safe(&self) -> Values {
+        let safe = |value: &str| {
+            value
+                .chars()
+                .map(|c| if is_invalid_in_file_name(c) { '_' } else { c })
+                .collect()
+        };
+
+        Values {
+            stem: safe(&self.stem),
+            name: safe(&self.name),
+            date: safe(&self.date),
+        }
+    }
+}
+
+fn is_invalid_in_file_name(c: char) -> bool {
+    c.is_control() || INVALID_FILE_NAME_CHARS.contains(&c)
+}
+
+fn render(template: &str, values: &Values, counter: u32) -> Result<String, SaveError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+
+        let end = rest[start..].find('}').ok_or_else(|| {
+            SaveError::InvalidTemplate(format!("unclosed placeholder in '{}'", template))
+        })?;
+
+        match &rest[start + 1..start + end] {
+            "stem" => rendered.push_str(&values.stem),
+            "name" => rendered.push_str(&values.name),
+            "date" => rendered.push_str(&values.date),
+            "n" if counter > 1 => rendered.push_str(&counter.to_string()),
+            "n" => (),
+            other => {
+                return Err(SaveError::InvalidTemplate(format!(
+                    "unknown placeholder {{{}}}, expected {{stem}}, {{name}}, {{date}} or {{n}}",
+                    other
+                )))
+            }
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn header() -> Header {
+        Header {
+            name: "Save Name".to_string(),
+            compatibility_version: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn find_save_name_no_file() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("find_save_name_no_files")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+
+        let (a, b) = NamingTemplate::default()
+            .find_free_name(&file_path, &header())
+            .await
+            .unwrap();
+
+        assert_eq!(a, "Save Name - Edited".to_string());
+        assert_eq!(b, tmp_dir.path().join("Save Game__Copy.zks"));
+    }
+
+    #[tokio::test]
+    async fn find_save_name_one_file() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("find_save_name_one_file")
+            .tempdir()
+            .unwrap();
+        File::create(tmp_dir.path().join("Save Game__Copy.zks")).unwrap();
+
+        let (a, b) = NamingTemplate::default()
+            .find_free_name(&tmp_dir.path().join("Save Game.zks"), &header())
+            .await
+            .unwrap();
+
+        assert_eq!(a, "Save Name - Edited 2".to_string());
+        assert_eq!(b, tmp_dir.path().join("Save Game__Copy2.zks"));
+    }
+
+    #[tokio::test]
+    async fn find_save_name_has_no_cap() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("find_save_name_has_no_cap")
+            .tempdir()
+            .unwrap();
+        File::create(tmp_dir.path().join("Save Game__Copy.zks")).unwrap();
+        for n in 2..=15 {
+            File::create(tmp_dir.path().join(format!("Save Game__Copy{}.zks", n))).unwrap();
+        }
+
+        let (_, b) = NamingTemplate::default()
+            .find_free_name(&tmp_dir.path().join("Save Game.zks"), &header())
+            .await
+            .unwrap();
+
+        assert_eq!(b, tmp_dir.path().join("Save Game__Copy16.zks"));
+    }
+
+    #[tokio::test]
+    async fn find_save_name_without_counter() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("find_save_name_without_counter")
+            .tempdir()
+            .unwrap();
+        let template = NamingTemplate {
+            file_name: "{name} {date}".to_string(),
+            save_name: "{name} (edited on {date})".to_string(),
+        };
+        let date = chrono::Local::now().format("%Y-%m-%d");
+
+        let (a, b) = template
+            .find_free_name(&tmp_dir.path().join("Save Game.zks"), &header())
+            .await
+            .unwrap();
+
+        assert_eq!(a, format!("Save Name (edited on {})", date));
+        assert_eq!(b, tmp_dir.path().join(format!("Save Name {}.zks", date)));
+
+        File::create(&b).unwrap();
+        let result = template
+            .find_free_name(&tmp_dir.path().join("Save Game.zks"), &header())
+            .await;
+
+        assert!(matches!(result, Err(SaveError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let preview = |file_name: &str| {
+            let template = NamingTemplate {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            };
+            template.preview(Path::new("Save Game.zks"), &header())
+        };
+
+        assert!(preview("{stem}_{count}").is_err());
+        assert!(preview("{stem").is_err());
+        assert!(preview("../{stem}").is_err());
+        assert!(preview("{n}").is_err());
+        assert!(preview("{stem}: edited").is_err());
+        assert_eq!(
+            preview("{stem} edited.zks").unwrap(),
+            (
+                "Save Name - Edited".to_string(),
+                "Save Game edited.zks".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn file_names_only_use_valid_characters() {
+        let header = Header {
+            name: "Wintersun -19 Lamashan (X) 4715 -08:36:14".to_string(),
+            compatibility_version: 1,
+            ..Default::default()
+        };
+        let template = NamingTemplate {
+            file_name: "{name}{n}...".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            template
+                .preview(Path::new("Save Game.zks"), &header)
+                .unwrap(),
+            (
+                "Wintersun -19 Lamashan (X) 4715 -08:36:14 - Edited".to_string(),
+                "Wintersun -19 Lamashan (X) 4715 -08_36_14.zks".to_string()
+            )
+        );
+    }
+}
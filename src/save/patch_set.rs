@@ -0,0 +1,150 @@
+//! Edits stored in a file, to be applied to another save.
+//!
+//! The file is a JSON object with the operations (see [Operation]) of each
+//! edited file of the save:
+//!
+//! ```json
+//! {"player": [{"op": "test", "path": "/Money", "value": 500},
+//!             {"op": "replace", "path": "/Money", "value": 10000}]}
+//! ```
+
+use super::{SaveDocuments, SaveError};
+use crate::json::{IndexedJson, JsonPatch, Operation};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PatchSet {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header: Vec<Operation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub party: Vec<Operation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub player: Vec<Operation>,
+}
+
+impl PatchSet {
+    /// The operations doing what the patches would do to the loaded save.
+    /// Each one is guarded by a `test` of the value it replaces, as left by
+    /// the previous patches: they are exported in order on a copy of the save.
+    pub fn export(
+        documents: &SaveDocuments,
+        player_patches: &[JsonPatch],
+        party_patches: &[JsonPatch],
+        header_patches: &[JsonPatch],
+    ) -> Result<PatchSet, SaveError> {
+        Ok(PatchSet {
+            header: export_operations("header.json", &documents.header, header_patches)?,
+            party: export_operations("party.json", &documents.party, party_patches)?,
+            player: export_operations("player.json", &documents.player, player_patches)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.party.is_empty() && self.player.is_empty()
+    }
+
+    pub async fn read(path: &Path) -> Result<PatchSet, SaveError> {
+        let content = tokio::fs::read(path).await?;
+
+        serde_json::from_slice(&content)
+            .map_err(|err| SaveError::InvalidPatchSet(path.to_path_buf(), err.to_string()))
+    }
+
+    pub async fn write(&self, path: &Path) -> Result<(), SaveError> {
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|err| SaveError::InvalidPatchSet(PathBuf::from(path), err.to_string()))?;
+
+        super::write_atomically(path, content).await
+    }
+}
+
+/// The operations of the patches of `file`, each one generated on a copy of
+/// `index` the previous ones were applied to
+fn export_operations(
+    file: &str,
+    index: &IndexedJson,
+    patches: &[JsonPatch],
+) -> Result<Vec<Operation>, SaveError> {
+    if patches.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut scratch = index.clone();
+    let mut operations = vec![];
+    for patch in patches {
+        let patch_operations = scratch
+            .operations(patch)
+            .map_err(|err| SaveError::json_error(file, err))?;
+        scratch
+            .apply(&patch_operations)
+            .map_err(|error| SaveError::Patches(file.to_string(), vec![error]))?;
+        operations.extend(patch_operations);
+    }
+
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::Fingerprint;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn patch_sets_can_be_written_and_read_back() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("patch_sets_can_be_written_and_read_back")
+            .tempdir()
+            .unwrap();
+        let path = tmp_dir.path().join("edits.json");
+
+        let document = |json: &str| Arc::new(IndexedJson::new(serde_json::from_str(json).unwrap()));
+        let documents = SaveDocuments {
+            header: document(r#"{"Name":"Before the siege"}"#),
+            party: document(r#"{"$id":"1"}"#),
+            player: document(r#"{"$id":"1","Money":500}"#),
+            fingerprint: Fingerprint::new(b"", None),
+            archive: Arc::from(&b""[..]),
+        };
+        let money = JsonPatch::id_at_pointer("1".into(), "/Money".into(), 1000.into());
+        let patch_set = PatchSet::export(&documents, &[money], &[], &[]).unwrap();
+
+        patch_set.write(&path).await.unwrap();
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!({"player": [
+                {"op": "test", "path": "/$id", "value": "1"},
+                {"op": "test", "$id": "1", "path": "/Money", "value": 500},
+                {"op": "replace", "$id": "1", "path": "/Money", "value": 1000},
+            ]})
+        );
+
+        assert_eq!(PatchSet::read(&path).await.unwrap(), patch_set);
+    }
+
+    #[test]
+    fn successive_edits_of_a_value_are_exported_in_order() {
+        let json = r#"{"$id":"1","Money":500}"#;
+        let document = || IndexedJson::new(serde_json::from_str(json).unwrap());
+        let documents = SaveDocuments {
+            header: Arc::new(IndexedJson::new(serde_json::json!({}))),
+            party: Arc::new(IndexedJson::new(serde_json::json!({}))),
+            player: Arc::new(document()),
+            fingerprint: Fingerprint::new(b"", None),
+            archive: Arc::from(&b""[..]),
+        };
+        let patches = [
+            JsonPatch::u64("/Money".into(), 1000),
+            JsonPatch::u64("/Money".into(), 2000),
+        ];
+
+        let patch_set = PatchSet::export(&documents, &patches, &[], &[]).unwrap();
+        let mut player = document();
+        player.apply(&patch_set.player).unwrap();
+
+        assert_eq!(player.json["Money"], 2000);
+    }
+}
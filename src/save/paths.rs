@@ -0,0 +1,62 @@
+//! Where the game stores its saves, per platform.
+//!
+//! a) Windows: %systemdrive%\users\%username%\AppData\LocalLow\Owlcat Games\Pathfinder Wrath Of The Righteous\Saved Games
+//! b) macOS: ~/Library/Application Support/unity.Owlcat Games.Pathfinder Wrath Of The Righteous/Saved Games/
+//! c) Linux: ~/.config/unity3d/Owlcat Games/Pathfinder Wrath Of The Righteous/Saved Games/
+use std::path::PathBuf;
+
+/// The directory in which the game writes its saves. `None` when the
+/// user's home directory cannot be determined.
+pub fn default_save_game_dir() -> Option<PathBuf> {
+    os::save_game()
+}
+
+/// The image of a user provided portrait, as found in the game's `Portraits`
+/// directory (a sibling of the saved games directory). `None` if missing.
+pub fn custom_portrait(id: &str) -> Option<PathBuf> {
+    let path = default_save_game_dir()?
+        .parent()?
+        .join("Portraits")
+        .join(id)
+        .join("Small.png");
+
+    path.is_file().then_some(path)
+}
+
+#[cfg(target_os = "windows")]
+mod win {
+    use std::path::PathBuf;
+
+    pub fn save_game() -> Option<PathBuf> {
+        // It would be better to have directly access to a data_locallow_dir() function
+        // Unfortunately the library doesn't offer it, so instead we do a path join
+        dirs::data_local_dir().map(|h| {
+            h.join("..\\LocalLow\\Owlcat Games\\Pathfinder Wrath Of The Righteous\\Saved Games")
+        })
+    }
+}
+#[cfg(target_os = "windows")]
+use win as os;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod mac {
+    use std::path::PathBuf;
+
+    pub fn save_game() -> Option<PathBuf> {
+        dirs::home_dir().map(|h| h.join("Library/Application Support/unity.Owlcat Games.Pathfinder Wrath Of The Righteous/Saved Games/"))
+    }
+}
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use mac as os;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::path::PathBuf;
+
+    pub fn save_game() -> Option<PathBuf> {
+        dirs::config_dir()
+            .map(|h| h.join("unity3d/Owlcat Games/Pathfinder Wrath Of The Righteous/Saved Games/"))
+    }
+}
+#[cfg(target_os = "linux")]
+use linux as os;
@@ -1,12 +1,14 @@
+use super::compression::EntryCompression;
+use super::integrity;
 use super::SaveError;
-use crate::data::Header;
-use crate::json::JsonPatch;
+use super::{BackupPolicy, Compression, Fingerprint, PatchSet, SaveDestination, SaveDocuments};
+use crate::data::{self, SaveFormat};
+use crate::json::{IndexedJson, JsonPatch};
 use async_channel::{Receiver, Sender};
-use iced::advanced::subscription::{EventStream, Hasher, Recipe};
-use std::hash::Hash;
 use std::io::Write;
 use std::ops::RangeInclusive;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, PartialEq, Copy)]
 pub enum SavingStep {
@@ -15,10 +17,12 @@ pub enum SavingStep {
     ExtractingParty,
     ExtractingHeader,
     ApplyingPatches,
+    CheckingIntegrity,
     SerializingJson,
     WritingArchive,
     WritingCustomFiles,
     FinishingArchive,
+    BackingUp,
     WritingToDisk,
 }
 
@@ -32,11 +36,13 @@ impl SavingStep {
             SavingStep::ExtractingParty => 2.0,
             SavingStep::ExtractingHeader => 3.0,
             SavingStep::ApplyingPatches => 4.0,
-            SavingStep::SerializingJson => 5.0,
-            SavingStep::WritingArchive => 6.0,
-            SavingStep::WritingCustomFiles => 7.0,
-            SavingStep::FinishingArchive => 8.0,
-            SavingStep::WritingToDisk => 9.0,
+            SavingStep::CheckingIntegrity => 5.0,
+            SavingStep::SerializingJson => 6.0,
+            SavingStep::WritingArchive => 7.0,
+            SavingStep::WritingCustomFiles => 8.0,
+            SavingStep::FinishingArchive => 9.0,
+            SavingStep::BackingUp => 10.0,
+            SavingStep::WritingToDisk => 11.0,
         }
     }
 
@@ -44,21 +50,42 @@ impl SavingStep {
     /// is not reachable as it's designed with the progress bar disappearing
     /// when the last step is done.
     pub fn steps_range() -> RangeInclusive<f32> {
-        0.0..=10.0
+        0.0..=12.0
     }
 }
 
+/// The result of a successful save
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavingDone {
+    /// Where the edited save has been written
+    pub path: PathBuf,
+    /// Size in bytes of the original save
+    pub size_before: u64,
+    /// Size in bytes of the edited save
+    pub size_after: u64,
+}
+
 pub struct SavingSaveGame {
     player_patches: Vec<JsonPatch>,
     party_patches: Vec<JsonPatch>,
+    header_patches: Vec<JsonPatch>,
     archive_path: PathBuf,
+    destination: SaveDestination,
+    backups: Option<BackupPolicy>,
+    expected: Option<Fingerprint>,
+    documents: Option<SaveDocuments>,
+    compression: Compression,
+    patch_set: PatchSet,
     tx: Sender<SavingStep>,
 }
 
 impl SavingSaveGame {
+    /// The header patches are applied after the copy has been named (see
+    /// [SaveDestination]), which lets them choose another name.
     pub fn new(
         player_patches: Vec<JsonPatch>,
         party_patches: Vec<JsonPatch>,
+        header_patches: Vec<JsonPatch>,
         archive_path: PathBuf,
     ) -> (SavingSaveGame, SaveNotifications) {
         let (tx, rx) = async_channel::bounded(1);
@@ -67,230 +94,660 @@ impl SavingSaveGame {
             SavingSaveGame {
                 player_patches,
                 party_patches,
+                header_patches,
                 archive_path,
+                destination: SaveDestination::default(),
+                backups: None,
+                expected: None,
+                documents: None,
+                compression: Compression::default(),
+                patch_set: PatchSet::default(),
                 tx,
             },
             SaveNotifications(rx),
         )
     }
 
-    pub async fn save(self) -> Result<(), SaveError> {
-        self.tx.send(SavingStep::LoadingArchive).await?;
-        let mut archive = super::load_archive(&self.archive_path).await?;
+    /// Where to write the edited save, a copy next to the original by default
+    pub fn with_destination(mut self, destination: SaveDestination) -> SavingSaveGame {
+        self.destination = destination;
+        self
+    }
+
+    /// Where to back up the save being replaced, if any. There are no backups
+    /// by default, see [BackupPolicy::in_default_directory] for the editor's.
+    pub fn with_backups(mut self, backups: Option<BackupPolicy>) -> SavingSaveGame {
+        self.backups = backups;
+        self
+    }
 
-        self.tx.send(SavingStep::ExtractingPlayer).await?;
-        let (_, mut player_index) = super::extract_player(&mut archive).await?;
+    /// Refuse to save, with [SaveError::ModifiedOnDisk], when the original save
+    /// no longer matches `fingerprint`. The patches would otherwise be applied
+    /// on top of a different game state than the one they were made for.
+    pub fn with_fingerprint(mut self, fingerprint: Fingerprint) -> SavingSaveGame {
+        self.expected = Some(fingerprint);
+        self
+    }
 
-        self.tx.send(SavingStep::ExtractingParty).await?;
-        let (_, mut party_index) = super::extract_party(&mut archive).await?;
+    /// Patch the documents read when loading the save, rather than parsing
+    /// them again. They are only used if the save is still the one they were
+    /// read from. Documents no longer shared elsewhere are patched in place,
+    /// the others are copied first.
+    pub fn with_documents(mut self, documents: SaveDocuments) -> SavingSaveGame {
+        self.documents = Some(documents);
+        self
+    }
 
-        self.tx.send(SavingStep::ExtractingHeader).await?;
-        let (header, mut header_index) = super::extract_header(&mut archive).await?;
-        let (new_save_name, new_file_path) =
-            find_appropriate_save_name(&self.archive_path, &header).await?;
+    /// How the JSON files are compressed, as in the original save by default.
+    /// The other files are always copied as they are.
+    pub fn with_compression(mut self, compression: Compression) -> SavingSaveGame {
+        self.compression = compression;
+        self
+    }
 
-        self.tx.send(SavingStep::ApplyingPatches).await?;
-        for patch in &self.player_patches {
-            player_index
-                .patch(patch)
-                .map_err(|err| SaveError::json_error("player.json", err))?;
-        }
-        for patch in &self.party_patches {
-            party_index
-                .patch(patch)
-                .map_err(|err| SaveError::json_error("party.json", err))?;
+    /// Edits read from a file, applied after the patches. A failing operation
+    /// (eg. a `test` on a save in another state) fails the save.
+    pub fn with_patch_set(mut self, patch_set: PatchSet) -> SavingSaveGame {
+        self.patch_set = patch_set;
+        self
+    }
+
+    /// The entries having edits to apply, the others can't be broken by saving
+    fn touched<'a>(
+        &self,
+        player: &'a IndexedJson,
+        party: &'a IndexedJson,
+        header: &'a IndexedJson,
+        renamed: bool,
+    ) -> Vec<(&'static str, &'a IndexedJson)> {
+        let player_touched = !self.player_patches.is_empty() || !self.patch_set.player.is_empty();
+        let party_touched = !self.party_patches.is_empty() || !self.patch_set.party.is_empty();
+        let header_touched =
+            renamed || !self.header_patches.is_empty() || !self.patch_set.header.is_empty();
+
+        [
+            ("player.json", player, player_touched),
+            ("party.json", party, party_touched),
+            ("header.json", header, header_touched),
+        ]
+        .iter()
+        .filter(|(_, _, touched)| *touched)
+        .map(|(file, index, _)| (*file, *index))
+        .collect()
+    }
+
+    /// Apply the patches to a copy of the archive, returning where the
+    /// newly written save game is.
+    pub async fn save(mut self) -> Result<SavingDone, SaveError> {
+        self.notify(SavingStep::LoadingArchive);
+        let (mut archive, fingerprint) = super::load_archive(&self.archive_path).await?;
+
+        if let Some(expected) = &self.expected {
+            if !expected.same_content(&fingerprint) {
+                return Err(SaveError::ModifiedOnDisk(self.archive_path.clone()));
+            }
         }
+
+        // Unwrapping the documents only copies them when they are still shared
+        let (header_doc, party_doc, player_doc) = match self.documents.take() {
+            Some(d) if d.fingerprint.same_content(&fingerprint) => {
+                (Some(d.header), Some(d.party), Some(d.player))
+            }
+            _ => (None, None, None),
+        };
+
+        self.notify(SavingStep::ExtractingPlayer);
+        let mut player_index = match player_doc {
+            Some(doc) => Arc::unwrap_or_clone(doc),
+            None => super::extract_json(&mut archive, "player.json")?,
+        };
+
+        self.notify(SavingStep::ExtractingParty);
+        let mut party_index = match party_doc {
+            Some(doc) => Arc::unwrap_or_clone(doc),
+            None => super::extract_json(&mut archive, "party.json")?,
+        };
+
+        self.notify(SavingStep::ExtractingHeader);
+        let (header, mut header_index) = match header_doc {
+            Some(doc) => (
+                data::read_header(&doc).map_err(|err| SaveError::json_error("header.json", err))?,
+                Arc::unwrap_or_clone(doc),
+            ),
+            None => super::extract_header(&mut archive)?,
+        };
+        let (new_save_name, new_file_path) = self
+            .destination
+            .resolve(&self.archive_path, &header)
+            .await?;
+
+        self.notify(SavingStep::ApplyingPatches);
+        let renamed = new_save_name.is_some();
+        // What was referenced, to find what the edits orphan
+        let referenced_before: Vec<(&str, Vec<_>)> = self
+            .touched(&player_index, &party_index, &header_index, renamed)
+            .into_iter()
+            .map(|(file, index)| (file, index.references().map(|(id, _)| id.clone()).collect()))
+            .collect();
+
+        player_index
+            .patch_all(&self.player_patches)
+            .map_err(|errors| SaveError::Patches("player.json".to_string(), errors))?;
+        party_index
+            .patch_all(&self.party_patches)
+            .map_err(|errors| SaveError::Patches("party.json".to_string(), errors))?;
+        // The name is patched first, so that the header patches can choose another one
+        let rename = new_save_name.map(|name| JsonPatch::string("/Name".into(), name));
         header_index
-            .patch(&JsonPatch::string("/Name".into(), new_save_name))
-            .map_err(|err| SaveError::json_error("header.json", err))?;
+            .patch_all(rename.iter().chain(&self.header_patches))
+            .map_err(|errors| SaveError::Patches("header.json".to_string(), errors))?;
+        for (index, file, operations) in [
+            (&mut player_index, "player.json", &self.patch_set.player),
+            (&mut party_index, "party.json", &self.patch_set.party),
+            (&mut header_index, "header.json", &self.patch_set.header),
+        ] {
+            index
+                .apply(operations)
+                .map_err(|error| SaveError::Patches(file.to_string(), vec![error]))?;
+        }
+
+        self.notify(SavingStep::CheckingIntegrity);
+        let format = SaveFormat::of(&header);
+        let touched = self.touched(&player_index, &party_index, &header_index, renamed);
+        let mut issues: Vec<_> = integrity::check_documents(&touched, &format)
+            .into_iter()
+            .filter(|issue| issue.problem.is_error())
+            .collect();
+        for ((file, index), (_, referenced)) in touched.iter().zip(&referenced_before) {
+            issues.extend(integrity::orphans(file, referenced, index));
+        }
+        if !issues.is_empty() {
+            // Only what the edits break is refused, not what was already there.
+            // The original entries are read again, as that's rarely needed.
+            let mut originals = vec![];
+            for (file, _) in &touched {
+                originals.push((*file, super::extract_json(&mut archive, file)?));
+            }
+            let originals: Vec<_> = originals
+                .iter()
+                .map(|(file, index)| (*file, index))
+                .collect();
+            let issues_before = integrity::check_documents(&originals, &format);
+
+            issues.retain(|issue| !issues_before.contains(issue));
+        }
+        if !issues.is_empty() {
+            return Err(SaveError::Integrity(issues));
+        }
 
-        self.tx.send(SavingStep::SerializingJson).await?;
+        self.notify(SavingStep::SerializingJson);
         let player_bytes = player_index
             .bytes()
-            .expect("player's JSON couldn't be serialized");
+            .map_err(|err| SaveError::json_error("player.json", err))?;
         let party_bytes = party_index
             .bytes()
-            .expect("party's JSON couldn't be serialized");
+            .map_err(|err| SaveError::json_error("party.json", err))?;
         let header_bytes = header_index
             .bytes()
-            .expect("header's JSON couldn't be serialized");
-
-        let not_modified_files: Vec<_> = archive
-            .file_names()
-            .filter(|n| n != &"header.json" && n != &"party.json" && n != &"player.json")
-            .map(str::to_string)
-            .collect();
+            .map_err(|err| SaveError::json_error("header.json", err))?;
 
         let mut write_buffer: Vec<u8> = vec![];
         let w = std::io::Cursor::new(&mut write_buffer);
         let mut zip = zip::ZipWriter::new(w);
 
-        self.tx.send(SavingStep::WritingArchive).await?;
-        for file in not_modified_files {
-            let mut original = archive
-                .by_name(&file)
-                .expect("Archive contained file by not really oO");
-            let mut options =
-                zip::write::SimpleFileOptions::default().compression_method(original.compression());
-
-            if let Some(lm) = original.last_modified() {
-                options = options.last_modified_time(lm);
+        self.notify(SavingStep::WritingArchive);
+        let original_bytes = super::archive_bytes(&archive);
+        // The unmodified entries (mostly the areas' state) are copied as they
+        // are, without decompressing and compressing them again.
+        for index in 0..archive.len() {
+            let original = archive.by_index_raw(index)?;
+            let name = original.name().to_string();
+
+            if !matches!(name.as_str(), "header.json" | "party.json" | "player.json") {
+                zip.raw_copy_file(original)
+                    .map_err(|err| SaveError::archive_error(&name, err))?;
             }
-
-            let options = match original.unix_mode() {
-                Some(m) => options.unix_permissions(m),
-                None => options,
-            };
-
-            zip.start_file(original.name(), options)
-                .expect("Starting file");
-            std::io::copy(&mut original, &mut zip).expect("Copying original file to new archive");
         }
 
-        self.tx.send(SavingStep::WritingCustomFiles).await?;
-        let options = zip::write::SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Stored);
-        zip.start_file("player.json", options)
-            .expect("Starting file player.json");
-        zip.write_all(&player_bytes)
-            .expect("Writing player_bytes to archive");
-
-        let options = zip::write::SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Stored);
-        zip.start_file("party.json", options)
-            .expect("Starting file party.json");
-        zip.write_all(&party_bytes)
-            .expect("Writing party_bytes to archive");
+        self.notify(SavingStep::WritingCustomFiles);
+        for (name, bytes) in [
+            ("player.json", &player_bytes),
+            ("party.json", &party_bytes),
+            ("header.json", &header_bytes),
+        ] {
+            let original = archive
+                .by_name(name)
+                .map(|entry| EntryCompression::of(&entry, &original_bytes))
+                .map_err(|err| SaveError::archive_error(name, err))?;
+            zip.start_file(name, self.compression.options(original))
+                .map_err(|err| SaveError::archive_error(name, err))?;
+            zip.write_all(bytes)
+                .map_err(|err| SaveError::archive_error(name, err))?;
+        }
 
-        let options = zip::write::SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Stored);
-        zip.start_file("header.json", options)
-            .expect("Starting file header.json");
-        zip.write_all(&header_bytes)
-            .expect("Writing header_bytes to archive");
+        self.notify(SavingStep::FinishingArchive);
+        zip.finish()
+            .map_err(|err| SaveError::archive_error(&new_file_path.to_string_lossy(), err))?;
 
-        self.tx.send(SavingStep::FinishingArchive).await?;
-        zip.finish().expect("Finishing zip archive");
+        if let Some(backups) = &self.backups {
+            if tokio::fs::metadata(&new_file_path).await.is_ok() {
+                self.notify(SavingStep::BackingUp);
+                backups.back_up(&new_file_path).await?;
+            }
+        }
 
-        self.tx.send(SavingStep::WritingToDisk).await?;
-        tokio::fs::write(new_file_path, write_buffer).await?;
+        let size_after = write_buffer.len() as u64;
+        self.notify(SavingStep::WritingToDisk);
+        super::write_atomically(&new_file_path, write_buffer).await?;
 
         // done, finally :)
-        Ok(())
+        Ok(SavingDone {
+            path: new_file_path,
+            size_before: original_bytes.len() as u64,
+            size_after,
+        })
+    }
+
+    // Only the latest step is of interest, and nobody listening isn't an error
+    fn notify(&self, step: SavingStep) {
+        let _ = self.tx.force_send(step);
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct SaveNotifications(Receiver<SavingStep>);
 
-impl Recipe for SaveNotifications {
-    type Output = SavingStep;
-
-    fn hash(&self, state: &mut Hasher) {
-        std::any::TypeId::of::<Self>().hash(state);
+impl SaveNotifications {
+    /// Wait for the next step of the saving process. Returns `None`
+    /// once the save is done (or has been dropped).
+    pub async fn next(&self) -> Option<SavingStep> {
+        self.0.recv().await.ok()
     }
 
-    fn stream(
-        self: Box<Self>,
-        _input: EventStream,
-    ) -> futures::stream::BoxStream<'static, Self::Output> {
-        Box::pin(self.0)
-    }
-}
-
-async fn find_appropriate_save_name(
-    archive_path: &Path,
-    header: &Header,
-) -> Result<(String, PathBuf), SaveError> {
-    // Let's iterate on copy number starting at one
-    // 1 is a special case where we don't include the number
-    // (i)   is define file name
-    // (ii)  check if file exists
-    //        if exists -> go to next number
-    //        if not -> next step
-    // (iii) Return not existing path and associated save game name
-    let base_file_name = archive_path
-        .file_stem()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| "Unknown Save Name".to_string());
-    let mut iteration = 1;
-
-    while iteration < 10 {
-        // define path
-        let file_name = if iteration == 1 {
-            format!("{}__Copy.zks", base_file_name)
-        } else {
-            format!("{}__Copy{}.zks", base_file_name, iteration)
-        };
-        let path = archive_path.with_file_name(file_name);
-
-        if tokio::fs::metadata(&path).await.is_ok() {
-            // file exists, try next one
-            iteration += 1;
-            continue;
-        } else {
-            // file don't exist, let's use it
-
-            let save_name = if iteration == 1 {
-                format!("{} - Edited", header.name)
-            } else {
-                format!("{} - Edited {}", header.name, iteration)
-            };
-
-            return Ok((save_name, path));
-        }
+    /// A stream of the saving steps, ending once the save is done.
+    pub fn into_stream(self) -> impl futures::Stream<Item = SavingStep> {
+        self.0
     }
-
-    Err(SaveError::Io(
-        "Can't find a good file name in 10 iterations".to_string(),
-    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::json::Value;
     use std::fs::File;
+    use std::path::Path;
+
+    const PARTY: &str = r#"{"$id":"1","m_EntityData":[{"$id":"2",
+        "$type":"Kingmaker.EntitySystem.Entities.UnitEntityData, Assembly-CSharp",
+        "Descriptor":{"$id":"3","CustomName":"Yaslin","Blueprint":"bp",
+        "Progression":{"$id":"4","Experience":1200},"Alignment":{"$id":"5","m_Vector":"0|0"},
+        "Stats":{"$id":"6","Strength":{"$id":"7","Type":"Strength","m_BaseValue":12}}}}]}"#;
+
+    const PLAYER: &str = r#"{"$id":"1","Money":500,"m_GlobalMaps":[],
+        "Kingdom":{"$id":"2","Resources":{"$id":"4","m_Finances":1,"m_Materials":2,"m_Favors":3},
+        "ResourcesPerTurn":{"$id":"5","m_Finances":1,"m_Materials":2,"m_Favors":3},"RecruitsManager":{"$id":"3","m_Pool":[],"m_Growth":[]}}}"#;
+
+    fn write_archive(path: &Path, header: &str) {
+        write_archive_with_party(path, header, PARTY)
+    }
+
+    fn write_archive_with_party(path: &Path, header: &str, party: &str) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+
+        for (name, content) in [
+            ("header.json", header),
+            ("party.json", party),
+            ("player.json", PLAYER),
+        ] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    fn read_entry(path: &Path, name: &str) -> Value {
+        let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+        serde_json::from_reader(archive.by_name(name).unwrap()).unwrap()
+    }
 
     #[tokio::test]
-    async fn find_save_name_no_file() {
+    async fn save_keeps_unknown_header_fields() {
         let tmp_dir = tempfile::Builder::new()
-            .prefix("find_save_name_no_files")
+            .prefix("save_keeps_unknown_header_fields")
             .tempdir()
             .unwrap();
         let file_path = tmp_dir.path().join("Save Game.zks");
-        let header = Header {
-            name: "Save Name".to_string(),
-            compatibility_version: 1,
+
+        let mut header: Value =
+            serde_json::from_str(include_str!("../../samples/header.json")).unwrap();
+        header["SomethingNew"] = serde_json::json!({"$id": "100", "Value": [1, 2]});
+        write_archive(&file_path, &header.to_string());
+
+        let money = JsonPatch::id_at_pointer("1".into(), "/Money".into(), 1000.into());
+        let (saving, _) = SavingSaveGame::new(vec![money], vec![], vec![], file_path);
+        let copy = saving.save().await.unwrap().path;
+
+        let mut expected = header;
+        expected["Name"] = "Wintersun -19 Lamashan (X) 4715 -08:36:14 - Edited".into();
+        assert_eq!(read_entry(&copy, "header.json"), expected);
+        assert_eq!(read_entry(&copy, "player.json")["Money"], 1000);
+    }
+
+    #[tokio::test]
+    async fn save_with_a_chosen_name() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("save_with_a_chosen_name")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let header_patches = vec![
+            JsonPatch::string("/Name".into(), "Before the siege".to_string()),
+            JsonPatch::string("/Description".into(), "All stats at 18".to_string()),
+        ];
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], header_patches, file_path);
+        let copy = saving.save().await.unwrap().path;
+
+        let header = read_entry(&copy, "header.json");
+        assert_eq!(header["Name"], "Before the siege");
+        assert_eq!(header["Description"], "All stats at 18");
+        assert_eq!(copy, tmp_dir.path().join("Save Game__Copy.zks"));
+    }
+
+    #[tokio::test]
+    async fn overwriting_backs_up_the_original() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("overwriting_backs_up_the_original")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+        let original = std::fs::read(&file_path).unwrap();
+        let backups = BackupPolicy {
+            directory: tmp_dir.path().join("backups"),
+            keep: 5,
         };
 
-        let (a, b) = find_appropriate_save_name(&file_path, &header)
+        let money = JsonPatch::id_at_pointer("1".into(), "/Money".into(), 1000.into());
+        let (saving, _) = SavingSaveGame::new(vec![money], vec![], vec![], file_path.clone());
+        let written = saving
+            .with_destination(SaveDestination::Overwrite)
+            .with_backups(Some(backups.clone()))
+            .save()
             .await
+            .unwrap()
+            .path;
+
+        assert_eq!(written, file_path);
+        assert_eq!(read_entry(&file_path, "player.json")["Money"], 1000);
+
+        let list = crate::save::list_backups(&backups.directory, &file_path)
+            .await
+            .unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(std::fs::read(&list[0].path).unwrap(), original);
+
+        // Only the edited save and the backup folder, no leftover temporary file
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn save_refuses_a_save_modified_since_loaded() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("save_refuses_a_save_modified")
+            .tempdir()
             .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+        let fingerprint = Fingerprint::read(&file_path).await.unwrap();
+
+        // The game saved again in the same slot
+        write_archive(&file_path, r#"{"Name":"Newer save"}"#);
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path.clone());
+        let result = saving.with_fingerprint(fingerprint).save().await;
 
-        assert_eq!(a, "Save Name - Edited".to_string());
-        assert_eq!(b, tmp_dir.path().join("Save Game__Copy.zks"));
+        assert_eq!(result, Err(SaveError::ModifiedOnDisk(file_path)));
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
     }
 
     #[tokio::test]
-    async fn find_save_name_one_file() {
+    async fn failing_patches_leave_nothing_behind() {
         let tmp_dir = tempfile::Builder::new()
-            .prefix("find_save_name_one_file")
+            .prefix("failing_patches_leave_nothing_behind")
             .tempdir()
             .unwrap();
-        let file_path = tmp_dir.path().join("Save Game__Copy.zks");
-        let mut tmp_file = File::create(&file_path).unwrap();
-        writeln!(tmp_file, "content doesn't matter").unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let missing = JsonPatch::u64("/Kingdom/Missing".into(), 1);
+        let (saving, _) = SavingSaveGame::new(vec![missing], vec![], vec![], file_path);
+        let result = saving.save().await;
 
-        let header = Header {
-            name: "Save Name".to_string(),
-            compatibility_version: 1,
+        assert_eq!(
+            result.unwrap_err().report(),
+            "1 edit(s) couldn't be applied to player.json: edit #1: no value at '/Kingdom/Missing'"
+        );
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn edits_corrupting_the_save_are_refused() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("edits_corrupting_the_save_are_refused")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let strength = JsonPatch::id_at_pointer("7".into(), "/m_BaseValue".into(), "18".into());
+        let (saving, _) = SavingSaveGame::new(vec![], vec![strength], vec![], file_path);
+        let result = saving.save().await;
+
+        match result {
+            Err(SaveError::Integrity(issues)) => {
+                assert_eq!(issues.len(), 1);
+                assert_eq!(issues[0].file, "party.json");
+                assert_eq!(
+                    issues[0].pointer.to_string(),
+                    "/m_EntityData/0/Descriptor/Stats/Strength"
+                );
+            }
+            other => panic!("Expected an integrity error, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn issues_already_in_the_save_are_not_refused() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("issues_already_in_the_save_are_not_refused")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        let party = PARTY.replace(
+            r#""Blueprint":"bp""#,
+            r#""Blueprint":"bp","Pet":{"$ref":"99"}"#,
+        );
+        write_archive_with_party(
+            &file_path,
+            include_str!("../../samples/header.json"),
+            &party,
+        );
+
+        let strength = JsonPatch::id_at_pointer("7".into(), "/m_BaseValue".into(), 14.into());
+        let (saving, _) = SavingSaveGame::new(vec![], vec![strength], vec![], file_path);
+        let done = saving.save().await.unwrap();
+
+        let party = read_entry(&done.path, "party.json");
+        assert_eq!(
+            party["m_EntityData"][0]["Descriptor"]["Stats"]["Strength"]["m_BaseValue"],
+            14
+        );
+    }
+
+    #[tokio::test]
+    async fn patch_sets_only_apply_to_the_values_they_were_made_for() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("patch_sets_only_apply_to_the_values_they_were_made_for")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let patch_set = |money: u64| PatchSet {
+            player: serde_json::from_value(serde_json::json!([
+                {"op": "test", "path": "/Money", "value": money},
+                {"op": "replace", "path": "/Money", "value": 10000},
+            ]))
+            .unwrap(),
+            ..Default::default()
         };
 
-        let (a, b) = find_appropriate_save_name(&tmp_dir.path().join("Save Game.zks"), &header)
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path.clone());
+        let result = saving.with_patch_set(patch_set(300)).save().await;
+        match result {
+            Err(SaveError::Patches(file, errors)) => {
+                assert_eq!(file, "player.json");
+                assert_eq!(errors[0].index, 0);
+            }
+            other => panic!("Expected the test to fail, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path);
+        let copy = saving
+            .with_patch_set(patch_set(500))
+            .save()
             .await
+            .unwrap()
+            .path;
+        assert_eq!(read_entry(&copy, "player.json")["Money"], 10000);
+    }
+
+    #[tokio::test]
+    async fn save_patches_the_loaded_documents() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("save_patches_the_loaded_documents")
+            .tempdir()
             .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let (loader, _) = crate::save::SaveLoader::new(file_path.clone());
+        let mut documents = loader.load().await.unwrap().documents;
+        // Tells the loaded documents apart from the ones in the archive
+        Arc::make_mut(&mut documents.player).json["Money"] = 777.into();
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path.clone());
+        let copy = saving
+            .with_documents(documents.clone())
+            .save()
+            .await
+            .unwrap()
+            .path;
+        assert_eq!(read_entry(&copy, "player.json")["Money"], 777);
+
+        // Once the save changed, the documents are outdated
+        write_archive(
+            &file_path,
+            r#"{"Name":"Newer save","CompatibilityVersion":1}"#,
+        );
+        std::fs::remove_file(&copy).unwrap();
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path);
+        let copy = saving.with_documents(documents).save().await.unwrap().path;
+        assert_eq!(read_entry(&copy, "player.json")["Money"], 500);
+    }
 
-        assert_eq!(a, "Save Name - Edited 2".to_string());
-        assert_eq!(b, tmp_dir.path().join("Save Game__Copy2.zks"));
+    #[tokio::test]
+    async fn unmodified_entries_are_copied_raw() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("unmodified_entries_are_copied_raw")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        // Add an area state, compressed and with its own metadata
+        let mut zip = zip::ZipWriter::new_append(
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&file_path)
+                .unwrap(),
+        )
+        .unwrap();
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .compression_level(Some(9))
+            .last_modified_time(zip::DateTime::from_date_and_time(2021, 9, 2, 13, 37, 0).unwrap())
+            .unix_permissions(0o600);
+        zip.start_file("Area_1.json", options).unwrap();
+        zip.write_all(r#"{"$id":"1","Objects":[1,2,3,4,5,6,7,8,9,10]}"#.repeat(100).as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path.clone());
+        let copy = saving.save().await.unwrap().path;
+
+        let raw_entry = |path: &Path| {
+            let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+            let index = archive.index_for_name("Area_1.json").unwrap();
+            let mut entry = archive.by_index_raw(index).unwrap();
+            let mut bytes = vec![];
+            std::io::Read::read_to_end(&mut entry, &mut bytes).unwrap();
+
+            (
+                bytes,
+                entry.compression(),
+                entry.crc32(),
+                entry.last_modified(),
+                entry.unix_mode(),
+            )
+        };
+
+        assert_eq!(raw_entry(&copy), raw_entry(&file_path));
+    }
+
+    #[tokio::test]
+    async fn json_files_keep_their_compression() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("json_files_keep_their_compression")
+            .tempdir()
+            .unwrap();
+        let file_path = tmp_dir.path().join("Save Game.zks");
+        write_archive(&file_path, include_str!("../../samples/header.json"));
+
+        let compression = |path: &Path| {
+            let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+            let method = archive.by_name("party.json").unwrap().compression();
+            method
+        };
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path.clone());
+        let done = saving.save().await.unwrap();
+        assert_eq!(compression(&done.path), zip::CompressionMethod::Deflated);
+        assert_eq!(
+            done.size_before,
+            std::fs::metadata(&file_path).unwrap().len()
+        );
+        assert_eq!(
+            done.size_after,
+            std::fs::metadata(&done.path).unwrap().len()
+        );
+
+        let (saving, _) = SavingSaveGame::new(vec![], vec![], vec![], file_path);
+        let stored = saving
+            .with_destination(SaveDestination::Path(tmp_dir.path().join("Stored.zks")))
+            .with_compression(Compression::Store)
+            .save()
+            .await
+            .unwrap();
+        assert_eq!(compression(&stored.path), zip::CompressionMethod::Stored);
+        assert!(stored.size_after > done.size_after);
     }
 }
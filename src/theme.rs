@@ -1,4 +1,4 @@
-use iced::widget::{button, container};
+use iced::widget::{button, container, progress_bar};
 use iced::{border, color, theme::Palette, Background, Border, Color, Font, Theme};
 
 // Do not forget to load custom fonts in the main function
@@ -133,3 +133,32 @@ pub fn army_widget(_theme: &Theme) -> container::Style {
         ..Default::default()
     }
 }
+
+pub fn success_notification(theme: &Theme) -> container::Style {
+    notification(theme.palette().success)
+}
+
+pub fn failure_notification(theme: &Theme) -> container::Style {
+    notification(theme.palette().danger)
+}
+
+fn notification(accent: Color) -> container::Style {
+    container::Style {
+        background: Some(Background::Color(SECONDARY_MENU_SURFACE)),
+        text_color: Some(Color::WHITE),
+        border: Border {
+            color: accent,
+            width: 1.,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    }
+}
+
+/// The progress bar of a save which failed, until its notification is dismissed
+pub fn failed_progress_bar(theme: &Theme) -> progress_bar::Style {
+    progress_bar::Style {
+        bar: Background::Color(theme.palette().danger),
+        ..progress_bar::primary(theme)
+    }
+}
@@ -1,13 +1,27 @@
 mod alignment;
+mod backups;
+mod browser;
 mod character;
+mod destination;
 mod editor;
+mod explorer;
+mod header;
 mod input;
+mod notification;
 mod player;
+mod preview;
 
 // module building blocks
 use alignment::AlignmentWidget;
+use backups::{BackupsWidget, Message as BackupsMessage};
 use character::{CharacterWidget, Message as CharacterMessage};
+use destination::{DestinationWidget, Message as DestinationMessage};
+use explorer::{ExplorerWidget, Message as ExplorerMessage};
+use header::{HeaderWidget, Message as HeaderMessage};
+use notification::{Message as NotificationMessage, SaveNotification};
 use player::{Message as PlayerMessage, PlayerWidget};
 
 // exposed components
-pub use editor::{EditorWidget, Message as EditorMessage};
+pub use browser::{Action as BrowserAction, Message as BrowserMessage, SaveBrowser};
+pub use editor::{EditorWidget, Message as EditorMessage, Reload};
+pub use preview::SavePreview;
@@ -25,7 +25,7 @@ impl AlignmentWidget {
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self) -> Element<'_, Message> {
         let canvas = Canvas::new(self)
             .width(Length::Fixed(200.0))
             .height(Length::Fixed(200.0));
@@ -127,7 +127,7 @@ impl Program<Message> for AlignmentWidget {
                 let path = builder.build();
 
                 // Make the lines and fill with the alignment color
-                frame.stroke(&path, thin_stroke.clone());
+                frame.stroke(&path, thin_stroke);
                 frame.fill(&path, color);
 
                 // Debug mode, label cell with its index
@@ -0,0 +1,249 @@
+use crate::dialog::{self, OpenError};
+use crate::save::{self, Backup, BackupPolicy, SaveError};
+use iced::{
+    widget::{button, checkbox, column, row, text, text_input},
+    Alignment, Element, Length, Task,
+};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    Listed(Box<Result<Vec<Backup>, SaveError>>),
+    Enabled(bool),
+    ChooseDirectory,
+    DirectoryChosen(Result<PathBuf, OpenError>),
+    KeepChanged(String),
+    Restore(PathBuf),
+    RestoreConfirmed(PathBuf, bool),
+    Restored(Box<Result<(), SaveError>>),
+    Reopen,
+}
+
+impl Message {
+    /// Whether the user asked to load the restored save
+    pub fn reopen(&self) -> bool {
+        matches!(self, Message(Msg::Reopen))
+    }
+}
+
+/// Backups made when the save is replaced, and their restoration
+pub struct BackupsWidget {
+    save: PathBuf,
+    enabled: bool,
+    /// `None` when no backup directory could be found nor has been chosen
+    directory: Option<PathBuf>,
+    keep: String,
+    backups: Result<Vec<Backup>, SaveError>,
+    restored: Option<Result<(), SaveError>>,
+}
+
+impl BackupsWidget {
+    pub fn new(save: PathBuf) -> (BackupsWidget, Task<Message>) {
+        let widget = BackupsWidget {
+            save,
+            enabled: true,
+            directory: save::default_backup_dir(),
+            keep: BackupPolicy::DEFAULT_KEEP.to_string(),
+            backups: Ok(vec![]),
+            restored: None,
+        };
+        let task = widget.refresh();
+
+        (widget, task)
+    }
+
+    /// How the save is backed up before being replaced, `None` meaning it isn't
+    pub fn policy(&self) -> Option<BackupPolicy> {
+        match self.enabled {
+            true => self.configured_policy(),
+            false => None,
+        }
+    }
+
+    fn configured_policy(&self) -> Option<BackupPolicy> {
+        Some(BackupPolicy {
+            directory: self.directory.clone()?,
+            keep: self.keep.parse().unwrap_or(BackupPolicy::DEFAULT_KEEP),
+        })
+    }
+
+    /// Keep the settings chosen for the same save, loaded again. The backups
+    /// themselves are listed anew.
+    pub fn carry_over(&mut self, previous: &BackupsWidget) -> Task<Message> {
+        self.enabled = previous.enabled;
+        self.directory = previous.directory.clone();
+        self.keep = previous.keep.clone();
+
+        self.refresh()
+    }
+
+    pub fn refresh(&self) -> Task<Message> {
+        let directory = match &self.directory {
+            Some(directory) => directory.clone(),
+            None => return Task::none(),
+        };
+        let save = self.save.clone();
+
+        Task::perform(
+            async move { save::list_backups(&directory, &save).await },
+            |result| Message(Msg::Listed(Box::new(result))),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message(Msg::Listed(result)) => {
+                self.backups = *result;
+                Task::none()
+            }
+            Message(Msg::Enabled(enabled)) => {
+                self.enabled = enabled;
+                Task::none()
+            }
+            Message(Msg::ChooseDirectory) => {
+                Task::perform(dialog::choose_directory(self.directory.clone()), |r| {
+                    Message(Msg::DirectoryChosen(r))
+                })
+            }
+            Message(Msg::DirectoryChosen(Ok(directory))) => {
+                self.directory = Some(directory);
+                self.refresh()
+            }
+            Message(Msg::DirectoryChosen(Err(error))) => {
+                log::debug!("No backup directory chosen: {}", error);
+                Task::none()
+            }
+            Message(Msg::KeepChanged(keep)) => {
+                if keep.chars().all(|c| c.is_ascii_digit()) {
+                    self.keep = keep;
+                }
+                Task::none()
+            }
+            Message(Msg::Restore(backup)) => {
+                let message = format!(
+                    "Replace {} with the backup {}? The current save is backed up first. \
+                     Edits made in the editor are kept, but not saved.",
+                    self.save.display(),
+                    backup.display()
+                );
+
+                Task::perform(dialog::confirm("Restore the backup", message), move |yes| {
+                    Message(Msg::RestoreConfirmed(backup.clone(), yes))
+                })
+            }
+            Message(Msg::RestoreConfirmed(backup, true)) => {
+                let save = self.save.clone();
+                // The current save is backed up even when backups are disabled
+                // for the editor's own saves, a restore shouldn't lose anything.
+                let policy = self.configured_policy();
+
+                Task::perform(
+                    async move { save::restore_backup(&backup, &save, policy.as_ref()).await },
+                    |result| Message(Msg::Restored(Box::new(result))),
+                )
+            }
+            Message(Msg::RestoreConfirmed(_, false)) => Task::none(),
+            Message(Msg::Restored(result)) => {
+                if let Err(error) = result.as_ref() {
+                    log::error!("Restoring the backup failed: {:?}", error);
+                }
+                self.restored = Some(*result);
+                self.refresh()
+            }
+            // Handled by the application, which loads the save again
+            Message(Msg::Reopen) => Task::none(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let directory = match &self.directory {
+            Some(directory) => directory.display().to_string(),
+            None => "No backup directory".to_string(),
+        };
+
+        let mut layout = column(vec![])
+            .spacing(6)
+            .push(text("Backups").size(24))
+            .push(
+                checkbox(
+                    "Back up the save before replacing it",
+                    self.enabled && self.directory.is_some(),
+                )
+                .on_toggle(|b| Message(Msg::Enabled(b))),
+            )
+            .push(
+                row(vec![])
+                    .spacing(8)
+                    .align_y(Alignment::Center)
+                    .push(text("Directory").width(Length::Fixed(200.0)))
+                    .push(text(directory))
+                    .push(button(text("Change").size(12)).on_press(Message(Msg::ChooseDirectory))),
+            )
+            .push(
+                row(vec![])
+                    .align_y(Alignment::Center)
+                    .push(text("Backups kept per save").width(Length::Fixed(200.0)))
+                    .push(
+                        text_input("Backups kept", &self.keep)
+                            .on_input(|s| Message(Msg::KeepChanged(s)))
+                            .width(Length::Fixed(60.0))
+                            .padding(4),
+                    ),
+            );
+
+        match &self.restored {
+            Some(Ok(())) => {
+                layout = layout.push(
+                    row(vec![])
+                        .spacing(8)
+                        .align_y(Alignment::Center)
+                        .push(text("Backup restored."))
+                        .push(
+                            button(text("Reopen the save").size(12)).on_press(Message(Msg::Reopen)),
+                        ),
+                )
+            }
+            Some(Err(error)) => {
+                layout = layout.push(text(format!(
+                    "Restoring the backup failed: {}",
+                    error.report()
+                )))
+            }
+            None => (),
+        }
+
+        match &self.backups {
+            Ok(backups) if backups.is_empty() => {
+                layout = layout.push(text("This save hasn't been backed up yet").size(12))
+            }
+            Ok(backups) => {
+                for backup in backups {
+                    layout = layout.push(
+                        row(vec![])
+                            .spacing(8)
+                            .align_y(Alignment::Center)
+                            .push(
+                                text(backup.created.format("%Y-%m-%d %H:%M:%S").to_string())
+                                    .width(Length::Fixed(200.0)),
+                            )
+                            .push(
+                                button(text("Restore").size(12))
+                                    .on_press(Message(Msg::Restore(backup.path.clone()))),
+                            ),
+                    );
+                }
+            }
+            Err(error) => {
+                layout = layout.push(text(format!(
+                    "Couldn't list the backups: {}",
+                    error.report()
+                )))
+            }
+        }
+
+        layout.into()
+    }
+}
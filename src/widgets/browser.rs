@@ -0,0 +1,437 @@
+use super::SavePreview;
+use crate::data::{self, SaveType};
+use crate::save::{self, Campaign, SaveError, SaveSummary};
+use crate::theme::{self, BOOKLETTER_1911};
+use iced::{
+    alignment,
+    widget::{button, checkbox, column, container, pick_list, row, scrollable, text, text_input},
+    Alignment, Element, Length, Task,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    Listed(Box<Result<Vec<SaveSummary>, SaveError>>),
+    Refresh,
+    FilterChanged(String),
+    TypeFilterChanged(TypeFilter),
+    GroupByCampaign(bool),
+    ToggleCampaign(Option<String>),
+    SortBy(SortColumn),
+    Open(PathBuf),
+    OpenFileDialog,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortColumn {
+    Name,
+    Character,
+    Type,
+    Date,
+}
+
+impl std::fmt::Display for SortColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortColumn::Name => write!(f, "Name"),
+            SortColumn::Character => write!(f, "Character"),
+            SortColumn::Type => write!(f, "Type"),
+            SortColumn::Date => write!(f, "Saved at"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeFilter {
+    All,
+    Only(SaveType),
+}
+
+impl TypeFilter {
+    const ALL: [TypeFilter; 5] = [
+        TypeFilter::All,
+        TypeFilter::Only(SaveType::Manual),
+        TypeFilter::Only(SaveType::Quick),
+        TypeFilter::Only(SaveType::Auto),
+        TypeFilter::Only(SaveType::Other),
+    ];
+
+    fn matches(&self, save: &SaveSummary) -> bool {
+        match self {
+            TypeFilter::All => true,
+            TypeFilter::Only(tpe) => save.header.save_type == Some(*tpe),
+        }
+    }
+}
+
+impl std::fmt::Display for TypeFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeFilter::All => write!(f, "All types"),
+            TypeFilter::Only(tpe) => write!(f, "{}", tpe),
+        }
+    }
+}
+
+/// What the parent is expected to do once the browser processed a message.
+pub enum Action {
+    None,
+    Run(Task<Message>),
+    Open(PathBuf),
+    OpenFileDialog,
+}
+
+enum Listing {
+    NoDirectory,
+    Loading,
+    Loaded(Vec<SaveSummary>),
+    Failed(SaveError),
+}
+
+/// List the saves found in the game's directory, along with what they are
+/// (name, character, type, when it was made) instead of their file names.
+pub struct SaveBrowser {
+    directory: Option<PathBuf>,
+    listing: Listing,
+    previews: HashMap<PathBuf, SavePreview>,
+    filter: String,
+    type_filter: TypeFilter,
+    sort: SortColumn,
+    ascending: bool,
+    group_by_campaign: bool,
+    /// Campaigns (by game id) showing all their saves instead of the newest one
+    expanded_campaigns: HashSet<Option<String>>,
+}
+
+impl SaveBrowser {
+    pub fn new(directory: Option<PathBuf>) -> (SaveBrowser, Task<Message>) {
+        let mut browser = SaveBrowser {
+            directory,
+            listing: Listing::NoDirectory,
+            previews: HashMap::new(),
+            filter: String::new(),
+            type_filter: TypeFilter::All,
+            sort: SortColumn::Date,
+            ascending: false,
+            group_by_campaign: false,
+            expanded_campaigns: HashSet::new(),
+        };
+        let task = browser.refresh();
+
+        (browser, task)
+    }
+
+    fn refresh(&mut self) -> Task<Message> {
+        match &self.directory {
+            Some(directory) => {
+                let directory = directory.clone();
+                self.listing = Listing::Loading;
+
+                Task::perform(
+                    async move { save::list_saves(&directory).await },
+                    |result| Message(Msg::Listed(Box::new(result))),
+                )
+            }
+            None => {
+                self.listing = Listing::NoDirectory;
+                Task::none()
+            }
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Action {
+        match message {
+            Message(Msg::Listed(result)) => {
+                self.listing = match *result {
+                    Ok(saves) => {
+                        self.previews = saves
+                            .iter()
+                            .map(|s| (s.path.clone(), SavePreview::new(s)))
+                            .collect();
+                        Listing::Loaded(saves)
+                    }
+                    Err(error) => Listing::Failed(error),
+                };
+                Action::None
+            }
+            Message(Msg::Refresh) => Action::Run(self.refresh()),
+            Message(Msg::FilterChanged(filter)) => {
+                self.filter = filter;
+                Action::None
+            }
+            Message(Msg::TypeFilterChanged(type_filter)) => {
+                self.type_filter = type_filter;
+                Action::None
+            }
+            Message(Msg::GroupByCampaign(group)) => {
+                self.group_by_campaign = group;
+                Action::None
+            }
+            Message(Msg::ToggleCampaign(game_id)) => {
+                if !self.expanded_campaigns.remove(&game_id) {
+                    self.expanded_campaigns.insert(game_id);
+                }
+                Action::None
+            }
+            Message(Msg::SortBy(column)) => {
+                if self.sort == column {
+                    self.ascending = !self.ascending;
+                } else {
+                    self.sort = column;
+                    // Most recent first is what we want when looking at dates
+                    self.ascending = column != SortColumn::Date;
+                }
+                Action::None
+            }
+            Message(Msg::Open(path)) => Action::Open(path),
+            Message(Msg::OpenFileDialog) => Action::OpenFileDialog,
+        }
+    }
+
+    /// The saves matching the filter, in the selected order
+    fn visible_saves(&self) -> Vec<&SaveSummary> {
+        let saves = match &self.listing {
+            Listing::Loaded(saves) => saves,
+            _ => return vec![],
+        };
+
+        let filter = self.filter.to_lowercase();
+        let mut visible: Vec<_> = saves
+            .iter()
+            .filter(|s| self.type_filter.matches(s))
+            .filter(|s| filter.is_empty() || matches_filter(s, &filter))
+            .collect();
+
+        visible.sort_by(|a, b| {
+            let ordering = match self.sort {
+                SortColumn::Name => a.header.name.cmp(&b.header.name),
+                SortColumn::Character => a
+                    .header
+                    .player_character_name
+                    .cmp(&b.header.player_character_name),
+                SortColumn::Type => a.header.save_type.cmp(&b.header.save_type),
+                SortColumn::Date => a.modified.cmp(&b.modified),
+            };
+
+            if self.ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+
+        visible
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let toolbar = row(vec![])
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .push(
+                text_input("Filter by name, character, area or file", &self.filter)
+                    .on_input(|s| Message(Msg::FilterChanged(s)))
+                    .padding(8),
+            )
+            .push(pick_list(
+                &TypeFilter::ALL[..],
+                Some(self.type_filter),
+                |f| Message(Msg::TypeFilterChanged(f)),
+            ))
+            .push(
+                checkbox("Group by campaign", self.group_by_campaign)
+                    .on_toggle(|b| Message(Msg::GroupByCampaign(b))),
+            )
+            .push(
+                button(text("Refresh"))
+                    .on_press(Message(Msg::Refresh))
+                    .padding(8),
+            )
+            .push(
+                button(text("Open another file…"))
+                    .on_press(Message(Msg::OpenFileDialog))
+                    .padding(8),
+            );
+
+        let content: Element<_> = match &self.listing {
+            Listing::NoDirectory => text(
+                "The saved games directory couldn't be found, use \"Open another file…\" instead.",
+            )
+            .into(),
+            Listing::Loading => text("Looking for save games…").into(),
+            Listing::Failed(error) => text(format!(
+                "Couldn't list the saved games directory: {}",
+                error.report()
+            ))
+            .into(),
+            Listing::Loaded(_) => {
+                let saves = self.visible_saves();
+
+                if saves.is_empty() {
+                    text("No save game found").into()
+                } else {
+                    let rows = if self.group_by_campaign {
+                        // Within a campaign, saves are always listed from the newest
+                        save::group_by_campaign(saves)
+                            .iter()
+                            .flat_map(|c| self.campaign_rows(c))
+                            .collect()
+                    } else {
+                        saves
+                            .into_iter()
+                            .map(|s| self.save_row(s))
+                            .collect::<Vec<_>>()
+                    };
+
+                    column(vec![])
+                        .push(self.columns_header())
+                        .push(scrollable(column(rows)).height(Length::Fill))
+                        .into()
+                }
+            }
+        };
+
+        container(column(vec![]).spacing(8).push(toolbar).push(content))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// The campaign heading, followed by its newest save or all of them when expanded
+    fn campaign_rows<'a>(&'a self, campaign: &Campaign<'a>) -> Vec<Element<'a, Message>> {
+        let game_id = campaign.game_id.map(str::to_string);
+        let expanded = self.expanded_campaigns.contains(&game_id);
+
+        let label = format!(
+            "{} {} – {} save{}",
+            if expanded { "▼" } else { "▶" },
+            campaign.title(),
+            campaign.saves.len(),
+            if campaign.saves.len() > 1 { "s" } else { "" }
+        );
+
+        let heading = button(text(label).font(BOOKLETTER_1911).size(22))
+            .width(Length::Fill)
+            .padding(6)
+            .style(theme::secondary_menu_button)
+            .on_press(Message(Msg::ToggleCampaign(game_id)));
+
+        let shown = if expanded { campaign.saves.len() } else { 1 };
+
+        std::iter::once(heading.into())
+            .chain(campaign.saves.iter().take(shown).map(|s| self.save_row(s)))
+            .collect()
+    }
+
+    fn columns_header(&self) -> Element<'_, Message> {
+        let sort_button = |column: SortColumn, portion: u16| {
+            let label = if self.sort != column {
+                column.to_string()
+            } else if self.ascending {
+                format!("{} ▲", column)
+            } else {
+                format!("{} ▼", column)
+            };
+
+            button(text(label).size(14))
+                .width(Length::FillPortion(portion))
+                .style(theme::secondary_menu_button)
+                .on_press(Message(Msg::SortBy(column)))
+        };
+
+        row(vec![])
+            .push(sort_button(SortColumn::Name, 4))
+            .push(sort_button(SortColumn::Character, 2))
+            .push(sort_button(SortColumn::Type, 1))
+            .push(
+                text("Play time")
+                    .size(14)
+                    .width(Length::FillPortion(1))
+                    .align_y(alignment::Vertical::Center),
+            )
+            .push(sort_button(SortColumn::Date, 2))
+            .into()
+    }
+
+    fn save_row<'a>(&'a self, save: &'a SaveSummary) -> Element<'a, Message> {
+        let header = &save.header;
+        let optional = |s: &Option<String>| s.clone().unwrap_or_else(|| "-".to_string());
+
+        let area = header
+            .area_name_override
+            .as_ref()
+            .or(header.area.as_ref())
+            .map(|a| format!("{} – area {}", save.file_name(), a))
+            .unwrap_or_else(|| save.file_name());
+
+        let preview = self.previews.get(&save.path);
+
+        let mut name = column(vec![])
+            .width(Length::FillPortion(4))
+            .spacing(2)
+            .push(text(&header.name).font(BOOKLETTER_1911).size(20))
+            .push(text(area).size(12));
+
+        if let Some(preview) = preview {
+            name = name.push(preview.portraits(32.0));
+        }
+
+        let saved_at = header
+            .system_save_time
+            .as_ref()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        let content = row(vec![])
+            .align_y(Alignment::Center)
+            .spacing(8)
+            .push_maybe(preview.map(|p| p.thumbnail(96.0)))
+            .push(name)
+            .push(text(optional(&header.player_character_name)).width(Length::FillPortion(2)))
+            .push(
+                text(
+                    header
+                        .save_type
+                        .map(|t| t.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                )
+                .width(Length::FillPortion(1)),
+            )
+            .push(
+                text(
+                    header
+                        .game_total_time
+                        .as_ref()
+                        .map(data::format_game_time)
+                        .unwrap_or_else(|| "-".to_string()),
+                )
+                .width(Length::FillPortion(1)),
+            )
+            .push(text(saved_at).width(Length::FillPortion(2)));
+
+        button(content)
+            .width(Length::Fill)
+            .padding(6)
+            .style(theme::secondary_menu_button)
+            .on_press(Message(Msg::Open(save.path.clone())))
+            .into()
+    }
+}
+
+fn matches_filter(save: &SaveSummary, filter: &str) -> bool {
+    let header = &save.header;
+
+    [
+        Some(&header.name),
+        header.player_character_name.as_ref(),
+        header.area_name_override.as_ref(),
+        header.area.as_ref(),
+    ]
+    .iter()
+    .flatten()
+    .any(|s| s.to_lowercase().contains(filter))
+        || save.file_name().to_lowercase().contains(filter)
+}
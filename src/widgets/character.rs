@@ -2,10 +2,10 @@ use super::alignment;
 use super::input::labelled_input_number;
 use crate::data::Character;
 use crate::json::{Id, JsonPatch, JsonPointer};
-use crate::theme;
+use crate::theme::{self, BOOKLETTER_1911};
 use crate::widgets::AlignmentWidget;
 use iced::{
-    widget::{column, container, row},
+    widget::{column, container, row, text_input, Space},
     Alignment, Element, Length, Task,
 };
 
@@ -19,6 +19,7 @@ enum Msg {
         field: Field,
         value: u64,
     },
+    NameModified(String),
     AlignmentWheel(alignment::Message),
 }
 
@@ -133,6 +134,8 @@ impl Field {
 struct FieldValue {
     field: Field,
     value: u64,
+    /// The value read from the save, only modified values are saved
+    original: u64,
     disabled: bool,
     id: Id,
     ptr: JsonPointer,
@@ -180,14 +183,19 @@ impl FieldValue {
         FieldValue {
             field,
             value,
+            original: value,
             disabled,
             id,
             ptr,
         }
     }
 
+    fn is_modified(&self) -> bool {
+        !self.disabled && self.value != self.original
+    }
+
     fn change(&self) -> JsonPatch {
-        if self.disabled {
+        if !self.is_modified() {
             JsonPatch::None
         } else {
             JsonPatch::id_at_pointer(
@@ -209,7 +217,7 @@ impl FieldValue {
             input = input.disabled();
         }
 
-        iced::widget::component(input)
+        input.into()
     }
 }
 
@@ -228,6 +236,13 @@ impl FieldValue {
 */
 pub struct CharacterWidget {
     pub id: Id,
+    unique_id: Option<String>,
+    blueprint: String,
+
+    // Only characters created by the player have a custom name, companions
+    // use the one from their blueprint.
+    original_name: Option<String>,
+    name: String,
 
     // Abilities
     strength: FieldValue,
@@ -272,6 +287,10 @@ impl CharacterWidget {
     pub fn new(character: &Character) -> CharacterWidget {
         CharacterWidget {
             id: character.id.clone(),
+            unique_id: character.unique_id.clone(),
+            blueprint: character.blueprint.clone(),
+            original_name: character.name.clone(),
+            name: character.name.clone().unwrap_or_default(),
             experience: FieldValue::from_field(character, Field::Experience),
             mythic_experience: FieldValue::from_field(character, Field::MythicExperience),
             strength: FieldValue::from_field(character, Field::Strength),
@@ -304,7 +323,7 @@ impl CharacterWidget {
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self) -> Element<'_, Message> {
         let main_stats = row(vec![])
             .width(Length::Fill)
             .align_y(Alignment::Center)
@@ -352,11 +371,22 @@ impl CharacterWidget {
             .push(combat_stats)
             .push(skills_stats);
 
+        let name: Element<_> = match self.original_name {
+            Some(_) => text_input("Name", &self.name)
+                .on_input(|s| Message(Msg::NameModified(s)))
+                .font(BOOKLETTER_1911)
+                .size(24)
+                .padding(4)
+                .into(),
+            None => Space::new(Length::Shrink, Length::Shrink).into(),
+        };
+
         container(
             column(vec![])
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .padding(10)
+                .push(name)
                 .push(main_stats)
                 .push(statistics)
                 .push(
@@ -364,7 +394,7 @@ impl CharacterWidget {
                         .view()
                         .map(|m| Message(Msg::AlignmentWheel(m))),
                 )
-                .push(iced::widget::Space::new(Length::Fill, Length::Fill)),
+                .push(Space::new(Length::Fill, Length::Fill)),
         )
         .style(theme::main_pane)
         .into()
@@ -375,6 +405,7 @@ impl CharacterWidget {
             Message(Msg::StatisticModified { field, value }) => {
                 self.stat_view_for_field(&field).value = value;
             }
+            Message(Msg::NameModified(name)) => self.name = name,
             Message(Msg::AlignmentWheel(_m)) => {
                 // TODO Will be used when integrating drag & drop for the alignment pin
             }
@@ -382,36 +413,86 @@ impl CharacterWidget {
         Task::none()
     }
 
+    /// The new custom name, if the user changed it
+    pub fn renamed(&self) -> Option<&str> {
+        match &self.original_name {
+            Some(original) if *original != self.name => Some(&self.name),
+            _ => None,
+        }
+    }
+
     pub fn patches(&self) -> Vec<JsonPatch> {
+        let name = match self.renamed() {
+            Some(name) => JsonPatch::id_at_pointer(
+                self.id.clone(),
+                "/Descriptor/CustomName".into(),
+                name.into(),
+            ),
+            None => JsonPatch::None,
+        };
+
+        let mut patches = vec![name];
+        patches.extend(self.field_values().iter().map(|f| f.change()));
+
+        patches
+    }
+
+    /// Whether both widgets show the same character, possibly read from
+    /// different saves (which is why the `$id` can't be used).
+    pub fn same_character(&self, other: &CharacterWidget) -> bool {
+        match (&self.unique_id, &other.unique_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.blueprint == other.blueprint && self.original_name == other.original_name,
+        }
+    }
+
+    /// Apply the edits made in `previous` to this character
+    pub fn carry_over(&mut self, previous: &CharacterWidget) {
+        if let (Some(name), Some(_)) = (previous.renamed(), &self.original_name) {
+            self.name = name.to_string();
+        }
+
+        for field in previous.field_values() {
+            if field.is_modified() {
+                let current = self.stat_view_for_field(&field.field);
+
+                if !current.disabled {
+                    current.value = field.value;
+                }
+            }
+        }
+    }
+
+    fn field_values(&self) -> Vec<&FieldValue> {
         vec![
-            self.strength.change(),
-            self.dexterity.change(),
-            self.constitution.change(),
-            self.intelligence.change(),
-            self.wisdom.change(),
-            self.charisma.change(),
-            self.cmb.change(),
-            self.cmd.change(),
-            self.ac.change(),
-            self.bab.change(),
-            self.hp.change(),
-            self.initiative.change(),
-            self.save_fortitude.change(),
-            self.save_reflex.change(),
-            self.save_will.change(),
-            self.athletics.change(),
-            self.mobility.change(),
-            self.thievery.change(),
-            self.stealth.change(),
-            self.arcana.change(),
-            self.world.change(),
-            self.nature.change(),
-            self.religion.change(),
-            self.perception.change(),
-            self.persuasion.change(),
-            self.magic_device.change(),
-            self.experience.change(),
-            self.mythic_experience.change(),
+            &self.strength,
+            &self.dexterity,
+            &self.constitution,
+            &self.intelligence,
+            &self.wisdom,
+            &self.charisma,
+            &self.cmb,
+            &self.cmd,
+            &self.ac,
+            &self.bab,
+            &self.hp,
+            &self.initiative,
+            &self.save_fortitude,
+            &self.save_reflex,
+            &self.save_will,
+            &self.athletics,
+            &self.mobility,
+            &self.thievery,
+            &self.stealth,
+            &self.arcana,
+            &self.world,
+            &self.nature,
+            &self.religion,
+            &self.perception,
+            &self.persuasion,
+            &self.magic_device,
+            &self.experience,
+            &self.mythic_experience,
         ]
     }
 
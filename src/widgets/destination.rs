@@ -0,0 +1,176 @@
+use crate::data::Header;
+use crate::save::{Compression, NamingTemplate, SaveDestination};
+use iced::{
+    widget::{button, column, radio, row, text, text_input},
+    Alignment, Element, Length, Task,
+};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    ModeChanged(Mode),
+    CompressionChanged(Compression),
+    FileNameTemplateChanged(String),
+    SaveNameTemplateChanged(String),
+    ResetTemplate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Copy,
+    Overwrite,
+}
+
+/// How the "Save" action writes the edited save. Writing to an explicit path
+/// goes through "Save as…" instead.
+pub struct DestinationWidget {
+    archive_path: PathBuf,
+    header: Header,
+    mode: Mode,
+    template: NamingTemplate,
+    /// How the edited JSON files are compressed
+    compression: Compression,
+}
+
+impl DestinationWidget {
+    pub fn new(archive_path: PathBuf, header: Header) -> DestinationWidget {
+        DestinationWidget {
+            archive_path,
+            header,
+            mode: Mode::Copy,
+            template: NamingTemplate::default(),
+            compression: Compression::default(),
+        }
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn destination(&self) -> SaveDestination {
+        match self.mode {
+            Mode::Copy => SaveDestination::Copy(self.template.clone()),
+            Mode::Overwrite => SaveDestination::Overwrite,
+        }
+    }
+
+    /// What the "Save" action names the save when its name isn't edited
+    pub fn unchanged_name_hint(&self) -> String {
+        match self.mode {
+            Mode::Overwrite => "Left unchanged, the save keeps its name".to_string(),
+            Mode::Copy => match self.template.preview(&self.archive_path, &self.header) {
+                Ok((save_name, _)) => {
+                    format!("Left unchanged, the copy is named \"{}\"", save_name)
+                }
+                // The error is shown along with the template
+                Err(_) => String::new(),
+            },
+        }
+    }
+
+    /// Keep the choices made for the same save, loaded again
+    pub fn carry_over(&mut self, previous: &DestinationWidget) {
+        self.mode = previous.mode;
+        self.template = previous.template.clone();
+        self.compression = previous.compression;
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message(Msg::ModeChanged(mode)) => self.mode = mode,
+            Message(Msg::CompressionChanged(c)) => self.compression = c,
+            Message(Msg::FileNameTemplateChanged(t)) => self.template.file_name = t,
+            Message(Msg::SaveNameTemplateChanged(t)) => self.template.save_name = t,
+            Message(Msg::ResetTemplate) => self.template = NamingTemplate::default(),
+        };
+
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let mode = |label, mode| {
+            radio(label, mode, Some(self.mode), |m| {
+                Message(Msg::ModeChanged(m))
+            })
+        };
+
+        let mut layout = column(vec![])
+            .spacing(6)
+            .push(text("When saving").size(24))
+            .push(mode("Write a copy next to the original save", Mode::Copy))
+            .push(mode(
+                "Replace the original save (asks for a confirmation)",
+                Mode::Overwrite,
+            ));
+
+        if self.mode == Mode::Copy {
+            let template_input = |label, value, on_input: fn(String) -> Msg| {
+                row(vec![])
+                    .align_y(Alignment::Center)
+                    .push(text(label).width(Length::Fixed(200.0)))
+                    .push(
+                        text_input(label, value)
+                            .on_input(move |s| Message(on_input(s)))
+                            .padding(4),
+                    )
+            };
+
+            let preview = match self.template.preview(&self.archive_path, &self.header) {
+                Ok((save_name, file_name)) => {
+                    format!("The first copy is {} named \"{}\"", file_name, save_name)
+                }
+                Err(error) => error.report(),
+            };
+
+            layout = layout
+                .push(template_input(
+                    "File name",
+                    &self.template.file_name,
+                    Msg::FileNameTemplateChanged,
+                ))
+                .push(template_input(
+                    "Save name",
+                    &self.template.save_name,
+                    Msg::SaveNameTemplateChanged,
+                ))
+                .push(
+                    text(
+                        "Placeholders: {stem} original file name, {name} original save name, \
+                     {date} today's date, {n} copy number",
+                    )
+                    .size(12),
+                )
+                .push(
+                    row(vec![])
+                        .spacing(8)
+                        .align_y(Alignment::Center)
+                        .push(text(preview).size(12))
+                        .push(button(text("Reset").size(12)).on_press(Message(Msg::ResetTemplate))),
+                );
+        }
+
+        let compression = |label, compression| {
+            radio(label, compression, Some(self.compression), |c| {
+                Message(Msg::CompressionChanged(c))
+            })
+        };
+
+        layout = layout.push(
+            row(vec![])
+                .spacing(12)
+                .align_y(Alignment::Center)
+                .push(text("Compression").width(Length::Fixed(200.0)))
+                .push(compression(
+                    "As in the original save",
+                    Compression::Preserve,
+                ))
+                .push(compression("Deflate", Compression::Deflate))
+                .push(compression("Store", Compression::Store)),
+        );
+
+        layout.into()
+    }
+}
@@ -1,177 +1,695 @@
-use crate::data::{Character, Party, Player};
-use crate::json::Id;
-use crate::save::{SaveError, SaveNotifications, SavingSaveGame, SavingStep};
+use crate::data::{Character, Diagnostic, Header, Party, Player, SaveFormat};
+use crate::dialog::{self, OpenError};
+use crate::json::{Id, IndexedJson, JsonPatch};
+use crate::save::{
+    Fingerprint, PatchSet, SaveDestination, SaveDocuments, SaveError, SaveLoader,
+    SaveNotifications, SavingDone, SavingSaveGame, SavingStep,
+};
 use crate::theme::{self, BECKETT_FONT, BOOKLETTER_1911};
-use crate::widgets::{CharacterMessage, CharacterWidget, PlayerMessage, PlayerWidget};
+use crate::widgets::{
+    BackupsMessage, BackupsWidget, CharacterMessage, CharacterWidget, DestinationMessage,
+    DestinationWidget, ExplorerMessage, ExplorerWidget, HeaderMessage, HeaderWidget,
+    NotificationMessage, PlayerMessage, PlayerWidget, SaveNotification, SavePreview,
+};
 use iced::{
     alignment,
-    widget::{button, column, container, progress_bar, row, text},
+    widget::{button, column, container, progress_bar, row, scrollable, text},
     Alignment, Element, Length, Subscription, Task,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::vec;
 
 #[derive(Debug, Clone)]
 pub struct Message(Msg);
 
+impl Message {
+    /// Whether the save should be loaded again, replacing the editor
+    pub fn reload(&self) -> Option<Reload> {
+        match self {
+            Message(Msg::ReloadConfirmed(true)) => Some(Reload::KeepingEdits),
+            Message(Msg::Backups(msg)) if msg.reopen() => Some(Reload::DiscardingEdits),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reload {
+    DiscardingEdits,
+    /// The edits are applied again to the newly loaded save, see [EditorWidget::carry_over]
+    KeepingEdits,
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 enum Pane {
+    Header,
     Party,
     Crusade,
+    Explorer,
 }
 
 #[derive(Debug, Clone)]
 enum Msg {
     Save,
+    SaveAs,
+    SaveAsChosen(Result<PathBuf, OpenError>),
+    OverwriteConfirmed(bool),
     ChangeActivePane(Pane),
     SwitchCharacter(Id),
+    Header(HeaderMessage),
+    Destination(DestinationMessage),
+    Backups(BackupsMessage),
     CharacterMessage(CharacterMessage),
     Player(PlayerMessage),
+    Explorer(ExplorerMessage),
+    Notification(NotificationMessage),
     SavingChange(SavingStep),
-    SavingResult(Box<Result<(), SaveError>>),
+    SavingResult(Box<Result<SavingDone, SaveError>>),
+    ReloadConfirmed(bool),
+    SaveAnywayConfirmed(bool),
+    DocumentsReloaded(Box<Result<SaveDocuments, SaveError>>),
+    AllowSaving,
+    ExportEdits,
+    ExportPathChosen(Result<PathBuf, OpenError>),
+    EditsExported(Box<Result<PathBuf, SaveError>>),
+    ApplyEdits,
+    PatchFileChosen(Result<PathBuf, OpenError>),
+    PatchSetRead(Box<Result<PatchSet, SaveError>>),
 }
 
 pub struct EditorWidget {
     archive_path: PathBuf,
+    /// The documents read when loading, or after saving over the save
+    documents: SaveDocuments,
+    /// The save as it was when loaded, or last saved over by the editor
+    fingerprint: Fingerprint,
+    /// Where the save in progress (or the last one) is written
+    destination: Option<SaveDestination>,
     characters: Vec<Character>,
-    active_character: Id,
+    /// What couldn't be read when loading, and can't be edited
+    diagnostics: Vec<Diagnostic>,
+    format: SaveFormat,
+    /// Saving is disabled until the user accepts to edit an unsupported format
+    read_only: bool,
+    /// The character played by the user, when it could be identified
+    main_character: Option<Id>,
+    /// `None` when no character could be read
+    active_character: Option<Id>,
     active_pane: Pane,
     saving: Option<SaveNotifications>,
     save_progress: Option<SavingStep>,
-
+    /// The save failed, its progress is kept until the failure is dismissed
+    save_failed: bool,
+    notification: SaveNotification,
+    /// Edits read from a file, applied along with the editor's on the next save
+    patch_set: PatchSet,
+    /// Where the edits were last exported
+    exported: Option<PathBuf>,
+
+    header_widget: HeaderWidget,
+    destination_widget: DestinationWidget,
+    backups_widget: BackupsWidget,
     character_widgets: Vec<CharacterWidget>,
     player_widget: PlayerWidget,
+    explorer_widget: ExplorerWidget,
 }
 
 impl EditorWidget {
-    pub fn new(archive_path: PathBuf, party: Party, player: Player) -> EditorWidget {
-        let active_character = party.characters.first().unwrap().id.clone();
+    pub fn new(
+        archive_path: PathBuf,
+        documents: SaveDocuments,
+        header: Header,
+        party: Party,
+        player: Player,
+        preview: Option<SavePreview>,
+    ) -> (EditorWidget, Task<Message>) {
+        let active_character = party.characters.first().map(|c| c.id.clone());
         let character_widgets = party.characters.iter().map(CharacterWidget::new).collect();
+        let main_character = party.main_character(&header).map(|c| c.id.clone());
+        let destination_widget = DestinationWidget::new(archive_path.clone(), header.clone());
+        let (backups_widget, backups_task) = BackupsWidget::new(archive_path.clone());
+        let explorer_widget = ExplorerWidget::new(documents.clone());
+        let diagnostics: Vec<_> = header
+            .diagnostics
+            .iter()
+            .chain(&party.diagnostics)
+            .chain(&player.diagnostics)
+            .cloned()
+            .collect();
+        let format = SaveFormat::of(&header);
+        // Let the user know right away that some parts of the save are missing,
+        // or that its format isn't supported
+        let active_pane = match diagnostics.is_empty() && format.warning().is_none() {
+            true => Pane::Party,
+            false => Pane::Header,
+        };
 
-        EditorWidget {
+        let editor = EditorWidget {
             archive_path,
+            fingerprint: documents.fingerprint.clone(),
+            documents,
+            destination: None,
             characters: party.characters,
+            diagnostics,
+            format,
+            read_only: format.is_read_only(),
+            main_character,
             active_character,
-            active_pane: Pane::Party,
+            active_pane,
             saving: None,
             save_progress: None,
-
+            save_failed: false,
+            notification: SaveNotification::default(),
+            patch_set: PatchSet::default(),
+            exported: None,
+
+            destination_widget,
+            backups_widget,
+            header_widget: HeaderWidget::new(header, preview),
             character_widgets,
             player_widget: PlayerWidget::new(&player),
+            explorer_widget,
+        };
+        let task = backups_task.map(|msg| Message(Msg::Backups(msg)));
+
+        (editor, task)
+    }
+
+    pub fn archive_path(&self) -> &Path {
+        &self.archive_path
+    }
+
+    /// Apply the edits made in `previous`, an editor of the same save before
+    /// it was modified on disk, to this one. Edits are matched to what they
+    /// modify rather than to the save's `$id`, which the game renumbers on
+    /// every save; those which can't be matched are dropped.
+    pub fn carry_over(&mut self, previous: &EditorWidget) -> Task<Message> {
+        self.header_widget.carry_over(&previous.header_widget);
+        self.destination_widget
+            .carry_over(&previous.destination_widget);
+        self.player_widget.carry_over(&previous.player_widget);
+
+        for character in &previous.character_widgets {
+            match self
+                .character_widgets
+                .iter_mut()
+                .find(|c| c.same_character(character))
+            {
+                Some(current) => current.carry_over(character),
+                None => log::warn!(
+                    "Character {} not found anymore, its edits are lost",
+                    character.id
+                ),
+            }
         }
+
+        let active = previous
+            .character_widgets
+            .iter()
+            .find(|c| Some(&c.id) == previous.active_character.as_ref())
+            .and_then(|a| self.character_widgets.iter().find(|c| c.same_character(a)));
+        if let Some(active) = active {
+            self.active_character = Some(active.id.clone());
+        }
+        self.active_pane = previous.active_pane;
+        self.read_only = self.read_only && previous.read_only;
+        self.patch_set = previous.patch_set.clone();
+
+        Task::batch(vec![
+            self.backups_widget
+                .carry_over(&previous.backups_widget)
+                .map(|msg| Message(Msg::Backups(msg))),
+            self.explorer_widget
+                .carry_over(&previous.explorer_widget)
+                .map(|msg| Message(Msg::Explorer(msg))),
+        ])
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
         log::debug!("Message received: {:?}", message);
         match message {
-            Message(Msg::Save) => {
-                let (saving, receiver) = SavingSaveGame::new(
-                    self.player_widget.patches(),
-                    self.character_widgets
-                        .iter()
-                        .flat_map(|c| c.patches())
-                        .collect(),
-                    self.archive_path.clone(),
-                );
-                self.saving = Some(receiver);
-
-                Task::perform(saving.save(), |res| {
-                    Message(Msg::SavingResult(Box::new(res)))
+            Message(Msg::Save) => match self.destination_widget.destination() {
+                SaveDestination::Overwrite => {
+                    let message = format!(
+                        "Replace {} with the edited save?{}",
+                        self.archive_path.display(),
+                        match self.backups_widget.policy() {
+                            Some(_) => " The original is backed up first.",
+                            None => " The original will be lost.",
+                        }
+                    );
+
+                    Task::perform(dialog::confirm("Replace the save", message), |yes| {
+                        Message(Msg::OverwriteConfirmed(yes))
+                    })
+                }
+                destination => self.save(destination),
+            },
+            Message(Msg::OverwriteConfirmed(true)) => self.save(SaveDestination::Overwrite),
+            Message(Msg::OverwriteConfirmed(false)) => Task::none(),
+            Message(Msg::SaveAs) => {
+                Task::perform(dialog::save_file(self.archive_path.clone()), |r| {
+                    Message(Msg::SaveAsChosen(r))
                 })
             }
+            Message(Msg::SaveAsChosen(Ok(path))) => self.save(SaveDestination::Path(path)),
+            Message(Msg::SaveAsChosen(Err(error))) => {
+                log::debug!("No file chosen to save as: {}", error);
+                Task::none()
+            }
             Message(Msg::SavingChange(step)) => {
                 self.save_progress = Some(step);
                 Task::none()
             }
             Message(Msg::SavingResult(res)) => {
-                // TODO Keep the progress bar on fail (and change its color) ?
-                match *res {
-                    Ok(()) => log::debug!("Save Game modified successfully"),
-                    Err(err) => log::error!("Saving save game failed: {:?}", err),
+                let task = match *res {
+                    Ok(done) => {
+                        log::debug!("Save Game modified successfully to {:?}", done.path);
+                        let path = done.path.clone();
+                        self.notification.saved(done);
+                        self.patch_set = PatchSet::default();
+                        self.save_progress = None;
+                        // Replacing the save may have backed it up
+                        let refresh = self
+                            .backups_widget
+                            .refresh()
+                            .map(|msg| Message(Msg::Backups(msg)));
+
+                        if path == self.archive_path {
+                            // Our own changes aren't a modification from someone else,
+                            // and the next save is made on top of them
+                            let reload = Task::perform(
+                                async move {
+                                    let (loader, _) = SaveLoader::new(path);
+                                    loader.load().await.map(|done| done.documents)
+                                },
+                                |r| Message(Msg::DocumentsReloaded(Box::new(r))),
+                            );
+
+                            Task::batch(vec![refresh, reload])
+                        } else {
+                            refresh
+                        }
+                    }
+                    Err(SaveError::ModifiedOnDisk(path)) => {
+                        self.save_progress = None;
+                        let message = format!(
+                            "{} changed on disk since it was loaded, probably because the game \
+                             saved in the same slot.\n\nReload it and apply your edits again?",
+                            path.display()
+                        );
+
+                        Task::perform(dialog::confirm("The save changed", message), |yes| {
+                            Message(Msg::ReloadConfirmed(yes))
+                        })
+                    }
+                    Err(err) => {
+                        log::error!("Saving save game failed: {:?}", err);
+                        self.notification.failed(err, self.archive_path.clone());
+                        self.save_failed = true;
+                        Task::none()
+                    }
                 };
 
-                // File saved (or failed to), reset the progress bar
-                self.save_progress = None;
                 self.saving = None;
 
+                task
+            }
+
+            // Handled by the application, which loads the save again
+            Message(Msg::ReloadConfirmed(true)) => Task::none(),
+            Message(Msg::ReloadConfirmed(false)) => {
+                let message = "Save anyway? Your edits will be applied on top of the modified \
+                               save, which may not be the game state they were made for."
+                    .to_string();
+
+                Task::perform(dialog::confirm("The save changed", message), |yes| {
+                    Message(Msg::SaveAnywayConfirmed(yes))
+                })
+            }
+            Message(Msg::SaveAnywayConfirmed(true)) => match self.destination.clone() {
+                Some(destination) => self.start_saving(destination, false),
+                None => Task::none(),
+            },
+            Message(Msg::SaveAnywayConfirmed(false)) => Task::none(),
+            Message(Msg::DocumentsReloaded(result)) => match *result {
+                Ok(documents) => {
+                    self.fingerprint = documents.fingerprint.clone();
+                    self.documents = documents.clone();
+                    self.explorer_widget
+                        .set_documents(documents)
+                        .map(|msg| Message(Msg::Explorer(msg)))
+                }
+                Err(error) => {
+                    log::warn!("Couldn't read the saved file back: {:?}", error);
+                    Task::none()
+                }
+            },
+
+            Message(Msg::AllowSaving) => {
+                self.read_only = false;
                 Task::none()
             }
 
+            Message(Msg::ExportEdits) => {
+                let stem = self
+                    .archive_path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let default_path = self
+                    .archive_path
+                    .with_file_name(format!("{} - edits.json", stem));
+
+                Task::perform(dialog::save_patch_file(default_path), |r| {
+                    Message(Msg::ExportPathChosen(r))
+                })
+            }
+            Message(Msg::ExportPathChosen(Ok(path))) => {
+                let patch_set = PatchSet::export(
+                    &self.documents,
+                    &self.player_patches(),
+                    &self.party_patches(),
+                    &self.header_patches(),
+                );
+
+                match patch_set {
+                    Ok(patch_set) => Task::perform(
+                        async move { patch_set.write(&path).await.map(|_| path) },
+                        |r| Message(Msg::EditsExported(Box::new(r))),
+                    ),
+                    Err(error) => {
+                        self.notification.failed(error, self.archive_path.clone());
+                        Task::none()
+                    }
+                }
+            }
+            Message(Msg::ExportPathChosen(Err(error))) => {
+                log::debug!("No file chosen to export the edits: {}", error);
+                Task::none()
+            }
+            Message(Msg::EditsExported(result)) => {
+                match *result {
+                    Ok(path) => self.exported = Some(path),
+                    Err(error) => self.notification.failed(error, self.archive_path.clone()),
+                }
+                Task::none()
+            }
+            Message(Msg::ApplyEdits) => Task::perform(dialog::open_patch_file(), |r| {
+                Message(Msg::PatchFileChosen(r))
+            }),
+            Message(Msg::PatchFileChosen(Ok(path))) => {
+                Task::perform(async move { PatchSet::read(&path).await }, |r| {
+                    Message(Msg::PatchSetRead(Box::new(r)))
+                })
+            }
+            Message(Msg::PatchFileChosen(Err(error))) => {
+                log::debug!("No edits file chosen: {}", error);
+                Task::none()
+            }
+            Message(Msg::PatchSetRead(result)) => match *result {
+                // Saved right away, as they can't be reviewed in the editor
+                Ok(patch_set) => {
+                    self.patch_set = patch_set;
+                    self.update(Message(Msg::Save))
+                }
+                Err(error) => {
+                    self.notification.failed(error, self.archive_path.clone());
+                    Task::none()
+                }
+            },
+
             Message(Msg::ChangeActivePane(new_pane)) => {
                 self.active_pane = new_pane;
                 Task::none()
             }
             Message(Msg::SwitchCharacter(active_character_id)) => {
-                self.active_character = active_character_id;
+                self.active_character = Some(active_character_id);
 
                 Task::none()
             }
 
-            Message(Msg::CharacterMessage(msg)) => self
-                .active_character_widget_mut()
+            Message(Msg::Header(msg)) => self
+                .header_widget
+                .update(msg)
+                .map(|msg| Message(Msg::Header(msg))),
+            Message(Msg::Destination(msg)) => self
+                .destination_widget
                 .update(msg)
-                .map(|msg| Message(Msg::CharacterMessage(msg))),
+                .map(|msg| Message(Msg::Destination(msg))),
+            Message(Msg::Backups(msg)) => self
+                .backups_widget
+                .update(msg)
+                .map(|msg| Message(Msg::Backups(msg))),
+            Message(Msg::CharacterMessage(msg)) => match self.active_character_widget_mut() {
+                Some(widget) => widget
+                    .update(msg)
+                    .map(|msg| Message(Msg::CharacterMessage(msg))),
+                None => Task::none(),
+            },
             Message(Msg::Player(msg)) => self
                 .player_widget
                 .update(msg)
                 .map(|msg| Message(Msg::Player(msg))),
+            Message(Msg::Explorer(msg)) => self
+                .explorer_widget
+                .update(msg)
+                .map(|msg| Message(Msg::Explorer(msg))),
+            Message(Msg::Notification(msg)) => {
+                if msg.dismissed() && self.save_failed {
+                    self.save_progress = None;
+                    self.save_failed = false;
+                }
+
+                self.notification
+                    .update(msg)
+                    .map(|msg| Message(Msg::Notification(msg)))
+            }
+        }
+    }
+
+    fn save(&mut self, destination: SaveDestination) -> Task<Message> {
+        self.start_saving(destination, true)
+    }
+
+    /// Without `check_unmodified`, the edits are saved even if the original
+    /// save changed on disk since it was loaded.
+    fn start_saving(
+        &mut self,
+        destination: SaveDestination,
+        check_unmodified: bool,
+    ) -> Task<Message> {
+        let (saving, receiver) = SavingSaveGame::new(
+            self.player_patches(),
+            self.party_patches(),
+            self.header_patches(),
+            self.archive_path.clone(),
+        );
+        self.saving = Some(receiver);
+        self.save_progress = None;
+        self.save_failed = false;
+        self.destination = Some(destination.clone());
+
+        let mut saving = saving
+            .with_destination(destination)
+            .with_backups(self.backups_widget.policy())
+            .with_compression(self.destination_widget.compression())
+            .with_documents(self.documents.clone())
+            .with_patch_set(self.patch_set.clone());
+
+        if check_unmodified {
+            saving = saving.with_fingerprint(self.fingerprint.clone());
+        }
+
+        Task::perform(saving.save(), |res| {
+            Message(Msg::SavingResult(Box::new(res)))
+        })
+    }
+
+    fn player_patches(&self) -> Vec<JsonPatch> {
+        let mut patches = self.player_widget.patches();
+        patches.extend(self.explorer_widget.patches("player.json"));
+
+        patches
+    }
+
+    fn party_patches(&self) -> Vec<JsonPatch> {
+        let mut patches: Vec<_> = self
+            .character_widgets
+            .iter()
+            .flat_map(|c| c.patches())
+            .collect();
+        patches.extend(self.explorer_widget.patches("party.json"));
+
+        patches
+    }
+
+    /// The header edits, along with the new name of the main character
+    /// so that the game's load menu stays in sync with the party.
+    fn header_patches(&self) -> Vec<JsonPatch> {
+        let mut patches = self.header_widget.patches();
+        patches.extend(self.explorer_widget.patches("header.json"));
+
+        let renamed = self
+            .character_widgets
+            .iter()
+            .find(|c| Some(&c.id) == self.main_character.as_ref())
+            .and_then(|c| c.renamed());
+
+        if let (true, Some(name)) = (self.header_widget.sync_character_name(), renamed) {
+            patches.push(JsonPatch::string(
+                "/PlayerCharacterName".into(),
+                name.to_string(),
+            ));
         }
+
+        patches
     }
 
-    fn active_character_widget_mut(&mut self) -> &mut CharacterWidget {
-        let a = self.active_character.clone();
+    fn active_character_widget_mut(&mut self) -> Option<&mut CharacterWidget> {
+        let a = self.active_character.clone()?;
 
-        self.character_widgets
-            .iter_mut()
-            .find(|c| c.id == a)
-            .unwrap()
+        self.character_widgets.iter_mut().find(|c| c.id == a)
     }
 
-    pub fn view(&self) -> Element<Message> {
-        let mut container = row(vec![]).push(pane_selector(self.active_pane, self.save_progress));
+    pub fn view(&self) -> Element<'_, Message> {
+        let mut container = row(vec![]);
 
         match self.active_pane {
-            Pane::Party => {
-                let a = self.active_character.clone();
+            Pane::Header => {
+                let layout = column(vec![])
+                    .spacing(20)
+                    .padding(10)
+                    .push(
+                        self.header_widget
+                            .view(self.destination_widget.unchanged_name_hint())
+                            .map(|msg| Message(Msg::Header(msg))),
+                    )
+                    .push_maybe(format_view(&self.format, self.read_only))
+                    .push_maybe(diagnostics_view(&self.diagnostics))
+                    .push(edits_view(
+                        &self.patch_set,
+                        self.exported.as_deref(),
+                        self.read_only,
+                    ))
+                    .push(
+                        self.destination_widget
+                            .view()
+                            .map(|msg| Message(Msg::Destination(msg))),
+                    )
+                    .push(
+                        self.backups_widget
+                            .view()
+                            .map(|msg| Message(Msg::Backups(msg))),
+                    );
 
+                container = container.push(
+                    iced::widget::container(scrollable(layout))
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .style(theme::main_pane),
+                )
+            }
+            Pane::Party => {
                 // we unfortunately cannot use `active_character_mut` here because
                 // if we do we would borrow self multiple time (for some reason)
                 let character = self
                     .character_widgets
                     .iter()
-                    .find(|c| c.id == a)
-                    .unwrap()
-                    .view()
-                    .map(|msg| Message(Msg::CharacterMessage(msg)));
+                    .find(|c| Some(&c.id) == self.active_character.as_ref());
+
+                let character: Element<'_, Message> = match character {
+                    Some(character) => column(vec![])
+                        .push_maybe(shared_view(&self.documents.party, &character.patches()))
+                        .push(
+                            character
+                                .view()
+                                .map(|msg| Message(Msg::CharacterMessage(msg))),
+                        )
+                        .into(),
+                    None => iced::widget::container(text(
+                        "No character could be read, see the Info pane",
+                    ))
+                    .padding(20)
+                    .into(),
+                };
 
                 container = container
-                    .push(character_selector(&self.characters, &self.active_character))
+                    .push(character_selector(
+                        &self.characters,
+                        self.active_character.as_ref(),
+                    ))
                     .push(character);
             }
 
             Pane::Crusade => {
                 container = container.push(
-                    self.player_widget
-                        .view()
-                        .map(|msg| Message(Msg::Player(msg))),
+                    column(vec![])
+                        .push_maybe(shared_view(
+                            &self.documents.player,
+                            &self.player_widget.patches(),
+                        ))
+                        .push(
+                            self.player_widget
+                                .view()
+                                .map(|msg| Message(Msg::Player(msg))),
+                        ),
+                )
+            }
+
+            Pane::Explorer => {
+                container = container.push(
+                    iced::widget::container(
+                        self.explorer_widget
+                            .view()
+                            .map(|msg| Message(Msg::Explorer(msg))),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(theme::main_pane),
                 )
             }
         };
 
-        container.into()
+        let mut main = column(vec![]);
+        if let Some(notification) = self.notification.view() {
+            main = main.push(
+                iced::widget::container(notification.map(|msg| Message(Msg::Notification(msg))))
+                    .padding(10)
+                    .style(theme::main_pane),
+            );
+        }
+
+        row(vec![])
+            .push(pane_selector(
+                self.active_pane,
+                self.save_progress,
+                self.save_failed,
+                self.read_only,
+            ))
+            .push(main.push(container))
+            .into()
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
         match &self.saving {
-            Some(s) => iced::advanced::subscription::from_recipe(s.clone())
-                .map(|s| Message(Msg::SavingChange(s))),
+            Some(s) => Subscription::run_with_id(
+                std::any::TypeId::of::<SaveNotifications>(),
+                s.clone().into_stream(),
+            )
+            .map(|s| Message(Msg::SavingChange(s))),
             None => Subscription::none(),
         }
     }
 }
 
-fn pane_selector(active: Pane, save_progress: Option<SavingStep>) -> Element<'static, Message> {
-    let build_tile = |label: &'static str, message: Message, is_active: bool| {
+fn pane_selector(
+    active: Pane,
+    save_progress: Option<SavingStep>,
+    save_failed: bool,
+    read_only: bool,
+) -> Element<'static, Message> {
+    let build_tile = |label: &'static str, message: Message, is_disabled: bool| {
         let txt = text(label)
             .font(BECKETT_FONT)
             .size(30)
@@ -184,7 +702,7 @@ fn pane_selector(active: Pane, save_progress: Option<SavingStep>) -> Element<'st
             .padding(1)
             .style(theme::pane_selector_button);
 
-        if !is_active {
+        if !is_disabled {
             btn = btn.on_press(message);
         }
 
@@ -193,8 +711,10 @@ fn pane_selector(active: Pane, save_progress: Option<SavingStep>) -> Element<'st
 
     let go_to_pane = |target| {
         let label = match target {
+            Pane::Header => "Info",
             Pane::Party => "Party",
             Pane::Crusade => "Crusade",
+            Pane::Explorer => "JSON",
         };
 
         let is_active = target == active;
@@ -204,15 +724,22 @@ fn pane_selector(active: Pane, save_progress: Option<SavingStep>) -> Element<'st
 
     let mut layout = column(vec![])
         .align_x(Alignment::Start)
+        .push(go_to_pane(Pane::Header))
         .push(go_to_pane(Pane::Party))
         .push(go_to_pane(Pane::Crusade))
-        .push(build_tile("Save", Message(Msg::Save), false));
+        .push(go_to_pane(Pane::Explorer))
+        .push(build_tile("Save", Message(Msg::Save), read_only))
+        .push(build_tile("Save as", Message(Msg::SaveAs), read_only));
 
     if let Some(step) = save_progress {
-        let bar = progress_bar(SavingStep::steps_range(), step.number())
+        let mut bar = progress_bar(SavingStep::steps_range(), step.number())
             .width(Length::from(100))
             .height(Length::from(10));
 
+        if save_failed {
+            bar = bar.style(theme::failed_progress_bar);
+        }
+
         layout = layout.push(bar);
     }
 
@@ -222,14 +749,142 @@ fn pane_selector(active: Pane, save_progress: Option<SavingStep>) -> Element<'st
         .into()
 }
 
+/// Why the save may not be edited safely, if its format isn't supported
+fn format_view(format: &SaveFormat, read_only: bool) -> Option<Element<'static, Message>> {
+    let warning = format.warning()?;
+
+    let mut layout = column(vec![])
+        .spacing(6)
+        .push(text("Unsupported save format").size(24))
+        .push(text(format!("Careful, {}.", warning)));
+
+    if read_only {
+        layout = layout.push(
+            row(vec![])
+                .spacing(12)
+                .align_y(Alignment::Center)
+                .push(text("Saving is disabled to protect the save."))
+                .push(button(text("Edit anyway")).on_press(Message(Msg::AllowSaving))),
+        );
+    }
+
+    Some(layout.into())
+}
+
+/// Sharing the edits with another save, as a JSON Patch file
+fn edits_view<'a>(
+    patch_set: &PatchSet,
+    exported: Option<&Path>,
+    read_only: bool,
+) -> Element<'a, Message> {
+    let mut apply = button(text("Apply edits from a file…"));
+    if !read_only {
+        apply = apply.on_press(Message(Msg::ApplyEdits));
+    }
+
+    let mut layout = column(vec![])
+        .spacing(6)
+        .push(text("Edits file").size(24))
+        .push(text(
+            "The edits can be exported to a file, to apply them to another save. \
+             They are only applied if the values they modify didn't change.",
+        ))
+        .push(
+            row(vec![])
+                .spacing(12)
+                .push(button(text("Export edits…")).on_press(Message(Msg::ExportEdits)))
+                .push(apply),
+        );
+
+    if let Some(path) = exported {
+        layout = layout.push(text(format!("Edits exported to {}", path.display())).size(12));
+    }
+    if !patch_set.is_empty() {
+        layout =
+            layout.push(text("The edits from the file will be applied on the next save").size(12));
+    }
+
+    layout.into()
+}
+
+/// The parts of the save which couldn't be read, if any
+fn diagnostics_view(diagnostics: &[Diagnostic]) -> Option<Element<'static, Message>> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let mut layout = column(vec![])
+        .spacing(6)
+        .push(text("Skipped while loading").size(24))
+        .push(text(
+            "These parts of the save couldn't be read. They can't be edited, \
+             and are saved as they are.",
+        ));
+
+    for diagnostic in diagnostics {
+        layout = layout.push(text(diagnostic.to_string()).size(12));
+    }
+
+    Some(layout.into())
+}
+
+/// The edits modifying values which are also referenced elsewhere in the save,
+/// and so are modified there too
+fn shared_view(index: &IndexedJson, patches: &[JsonPatch]) -> Option<Element<'static, Message>> {
+    let shared: Vec<_> = patches
+        .iter()
+        .filter_map(|patch| {
+            let used_by = index.shared_with(patch);
+            if used_by.is_empty() {
+                return None;
+            }
+
+            let edited = match patch {
+                JsonPatch::Id { id, .. } => format!("The value with the $id {}", id),
+                JsonPatch::IdPointed { id, pointer, .. } => {
+                    format!("'{}' of the value with the $id {}", pointer, id)
+                }
+                JsonPatch::Pointer { pointer, .. } => format!("'{}'", pointer),
+                JsonPatch::None => return None,
+            };
+            let used_by: Vec<_> = used_by.iter().map(|p| format!("'{}'", p)).collect();
+
+            Some(format!("{} is also used by {}", edited, used_by.join(", ")))
+        })
+        .collect();
+
+    if shared.is_empty() {
+        return None;
+    }
+
+    let mut layout = column(vec![])
+        .spacing(6)
+        .push(text("Shared values").size(24))
+        .push(text(
+            "These edits modify values the save references elsewhere, \
+             where they will be modified too.",
+        ));
+    for line in shared {
+        layout = layout.push(text(line).size(12));
+    }
+
+    Some(
+        container(layout)
+            .width(Length::Fill)
+            .padding(10)
+            .style(theme::main_pane)
+            .into(),
+    )
+}
+
 fn character_selector<'a>(
     characters: &[Character],
-    active_character_id: &Id,
+    active_character_id: Option<&Id>,
 ) -> Element<'a, Message> {
     let mut col = column(vec![]).width(Length::from(170)).height(Length::Fill);
 
     for character in characters {
-        let active = character.id == *active_character_id;
+        let active = Some(&character.id) == active_character_id;
 
         let text = text(character.name())
             .font(BOOKLETTER_1911)
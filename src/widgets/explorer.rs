@@ -0,0 +1,853 @@
+use crate::json::{Id, IndexedJson, JsonPatch, JsonPointer, Value};
+use crate::save::{self, SaveDocuments, SaveError};
+use iced::{
+    widget::{button, column, pick_list, row, scrollable, text, text_input, Space},
+    Alignment, Element, Length, Task,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The children shown for a node, as the arrays of some entries are huge
+const MAX_CHILDREN: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    EntrySelected(String),
+    EntryRead(String, Box<Result<Content, SaveError>>),
+    Toggle(JsonPointer),
+    Select(JsonPointer),
+    Follow(JsonPointer),
+    InputChanged(String),
+    TypeSelected(ScalarType),
+    Apply,
+    Revert(JsonPointer),
+    CopyPointer,
+}
+
+/// An entry of the archive, which is much too big to be logged along with the messages
+#[derive(Clone)]
+struct Content(Arc<IndexedJson>);
+
+impl std::fmt::Debug for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Content(..)")
+    }
+}
+
+/// The type of a scalar, which edits keep unless it's `null`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    Null,
+    Boolean,
+    Number,
+    Text,
+}
+
+impl ScalarType {
+    const ALL: [ScalarType; 4] = [
+        ScalarType::Null,
+        ScalarType::Boolean,
+        ScalarType::Number,
+        ScalarType::Text,
+    ];
+
+    /// `None` for objects and arrays
+    fn of(json: &Value) -> Option<ScalarType> {
+        match json {
+            Value::Null => Some(ScalarType::Null),
+            Value::Bool(_) => Some(ScalarType::Boolean),
+            Value::Number(_) => Some(ScalarType::Number),
+            Value::String(_) => Some(ScalarType::Text),
+            Value::Array(_) | Value::Object(_) => None,
+        }
+    }
+
+    /// The value of this type written in `input`
+    fn parse(self, input: &str) -> Result<Value, String> {
+        match self {
+            ScalarType::Null => Ok(Value::Null),
+            ScalarType::Text => Ok(Value::from(input)),
+            ScalarType::Boolean => match input.trim() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(format!("'{}' isn't true or false", input)),
+            },
+            ScalarType::Number => match serde_json::from_str::<Value>(input) {
+                Ok(value) if value.is_number() => Ok(value),
+                _ => Err(format!("'{}' isn't a number", input)),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ScalarType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalarType::Null => write!(f, "null"),
+            ScalarType::Boolean => write!(f, "true or false"),
+            ScalarType::Number => write!(f, "number"),
+            ScalarType::Text => write!(f, "text"),
+        }
+    }
+}
+
+/// A scalar modified in the explorer
+struct RawEdit {
+    file: String,
+    pointer: JsonPointer,
+    /// The value it had when loaded
+    original: Value,
+    value: Value,
+}
+
+/// Browse any JSON entry of the save as a tree, for what the other panes
+/// don't show. The scalars of the entries the editor saves can be edited.
+pub struct ExplorerWidget {
+    documents: SaveDocuments,
+    entries: Result<Vec<String>, SaveError>,
+    /// The other entries already read from the archive of the documents
+    read_entries: HashMap<String, Arc<IndexedJson>>,
+    entry: String,
+    /// `None` while the entry is being read
+    content: Option<Result<Arc<IndexedJson>, SaveError>>,
+    expanded: HashSet<JsonPointer>,
+    selected: Option<JsonPointer>,
+    /// The new value of the selected node, as typed
+    input: String,
+    /// The type the input is read as, only chosen when the original value is `null`
+    input_type: ScalarType,
+    input_error: Option<String>,
+    edits: Vec<RawEdit>,
+}
+
+impl ExplorerWidget {
+    pub fn new(documents: SaveDocuments) -> ExplorerWidget {
+        let entry = "player.json".to_string();
+
+        ExplorerWidget {
+            content: document(&documents, &entry).map(Ok),
+            entries: save::json_entries(&documents),
+            documents,
+            read_entries: HashMap::new(),
+            entry,
+            expanded: HashSet::new(),
+            selected: None,
+            input: String::new(),
+            input_type: ScalarType::Null,
+            input_error: None,
+            edits: vec![],
+        }
+    }
+
+    /// Explore `documents`, those of the same save once written with the edits.
+    /// The edits it now contains are dropped.
+    pub fn set_documents(&mut self, documents: SaveDocuments) -> Task<Message> {
+        self.entries = save::json_entries(&documents);
+        self.read_entries.clear();
+        self.documents = documents;
+
+        let documents = &self.documents;
+        self.edits.retain(|edit| {
+            let current = document(documents, &edit.file)
+                .and_then(|index| edit.pointer.get(&index.json).cloned());
+            current.as_ref() != Some(&edit.value)
+        });
+
+        self.select_entry(self.entry.clone())
+    }
+
+    /// Keep the edits made in `previous`, an explorer of the same save before
+    /// it was modified on disk, whose values are still the ones they replace.
+    pub fn carry_over(&mut self, previous: &ExplorerWidget) -> Task<Message> {
+        for edit in &previous.edits {
+            let current = document(&self.documents, &edit.file)
+                .and_then(|index| edit.pointer.get(&index.json).cloned());
+
+            match current {
+                Some(current) if current == edit.original => self.edits.push(RawEdit {
+                    file: edit.file.clone(),
+                    pointer: edit.pointer.clone(),
+                    original: edit.original.clone(),
+                    value: edit.value.clone(),
+                }),
+                _ => log::warn!(
+                    "{} changed at {}, its edit is lost",
+                    edit.file,
+                    edit.pointer
+                ),
+            }
+        }
+
+        self.expanded = previous.expanded.clone();
+        self.selected = previous.selected.clone();
+        self.input = previous.input.clone();
+        self.input_type = previous.input_type;
+
+        self.select_entry(previous.entry.clone())
+    }
+
+    /// The edits of `file`, one of the entries the editor saves
+    pub fn patches(&self, file: &str) -> Vec<JsonPatch> {
+        self.edits
+            .iter()
+            .filter(|edit| edit.file == file)
+            .map(|edit| JsonPatch::Pointer {
+                pointer: edit.pointer.clone(),
+                new_value: edit.value.clone(),
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message(Msg::EntrySelected(entry)) if entry == self.entry => Task::none(),
+            Message(Msg::EntrySelected(entry)) => {
+                self.expanded.clear();
+                self.selected = None;
+                self.input.clear();
+                self.input_error = None;
+
+                self.select_entry(entry)
+            }
+            Message(Msg::EntryRead(entry, result)) => {
+                // The user may have chosen another entry in the meantime
+                let result = result.map(|content| content.0);
+                if let Ok(index) = &result {
+                    self.read_entries.insert(entry.clone(), index.clone());
+                }
+                if entry == self.entry {
+                    self.content = Some(result);
+                }
+                Task::none()
+            }
+            Message(Msg::Toggle(pointer)) => {
+                if !self.expanded.remove(&pointer) {
+                    self.expanded.insert(pointer);
+                }
+                Task::none()
+            }
+            Message(Msg::Select(pointer)) => {
+                self.select(pointer);
+                Task::none()
+            }
+            Message(Msg::Follow(target)) => {
+                let mut ancestor = target.parent();
+                while let Some(pointer) = ancestor {
+                    ancestor = pointer.parent();
+                    self.expanded.insert(pointer);
+                }
+                self.expanded.insert(target.clone());
+                self.select(target);
+                Task::none()
+            }
+            Message(Msg::InputChanged(input)) => {
+                self.input = input;
+                self.input_error = None;
+                Task::none()
+            }
+            Message(Msg::TypeSelected(input_type)) => {
+                self.input_type = input_type;
+                self.input_error = None;
+                Task::none()
+            }
+            Message(Msg::Apply) => {
+                self.apply_input();
+                Task::none()
+            }
+            Message(Msg::Revert(pointer)) => {
+                self.remove_edit(&pointer);
+                self.select(pointer);
+                Task::none()
+            }
+            Message(Msg::CopyPointer) => match &self.selected {
+                Some(pointer) => iced::clipboard::write(pointer.to_string()),
+                None => Task::none(),
+            },
+        }
+    }
+
+    fn select_entry(&mut self, entry: String) -> Task<Message> {
+        self.content = document(&self.documents, &entry)
+            .or_else(|| self.read_entries.get(&entry).cloned())
+            .map(Ok);
+        self.entry = entry.clone();
+
+        if self.content.is_some() {
+            return Task::none();
+        }
+
+        // Parsing the bigger entries takes a while
+        let documents = self.documents.clone();
+        Task::perform(
+            async move {
+                let result = save::read_json_entry(&documents, &entry);
+                (entry, result.map(|index| Content(Arc::new(index))))
+            },
+            |(entry, result)| Message(Msg::EntryRead(entry, Box::new(result))),
+        )
+    }
+
+    fn select(&mut self, pointer: JsonPointer) {
+        let value = self.value(&pointer);
+        self.input_type = value
+            .as_ref()
+            .and_then(ScalarType::of)
+            .unwrap_or(ScalarType::Null);
+        self.input = match value {
+            Some(Value::String(s)) => s,
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        self.input_error = None;
+        self.selected = Some(pointer);
+    }
+
+    /// The value at `pointer` in the explored entry, with its edit if it has one
+    fn value(&self, pointer: &JsonPointer) -> Option<Value> {
+        match self.edit(pointer) {
+            Some(edit) => Some(edit.value.clone()),
+            None => pointer.get(&self.index()?.json).cloned(),
+        }
+    }
+
+    fn edit(&self, pointer: &JsonPointer) -> Option<&RawEdit> {
+        self.edits
+            .iter()
+            .find(|edit| edit.file == self.entry && &edit.pointer == pointer)
+    }
+
+    fn remove_edit(&mut self, pointer: &JsonPointer) {
+        let entry = &self.entry;
+        self.edits
+            .retain(|edit| &edit.file != entry || &edit.pointer != pointer);
+    }
+
+    fn index(&self) -> Option<&IndexedJson> {
+        match &self.content {
+            Some(Ok(index)) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Only the entries patched when saving can be edited
+    fn is_editable(&self) -> bool {
+        document(&self.documents, &self.entry).is_some()
+    }
+
+    /// Turn the input into an edit of the selected scalar, which keeps its
+    /// type unless it was `null`
+    fn apply_input(&mut self) {
+        let pointer = match &self.selected {
+            Some(pointer) => pointer.clone(),
+            None => return,
+        };
+        let original = match self.index().and_then(|index| pointer.get(&index.json)) {
+            Some(original) => original.clone(),
+            None => return,
+        };
+
+        let input_type = match ScalarType::of(&original) {
+            Some(ScalarType::Null) => self.input_type,
+            Some(original_type) => original_type,
+            None => return,
+        };
+        let value = match input_type.parse(&self.input) {
+            Ok(value) => value,
+            Err(error) => {
+                self.input_error = Some(error);
+                return;
+            }
+        };
+
+        self.remove_edit(&pointer);
+        if value != original {
+            self.edits.push(RawEdit {
+                file: self.entry.clone(),
+                pointer,
+                original,
+                value,
+            });
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let entries = self.entries.as_ref().cloned().unwrap_or_default();
+        let mut header = row(vec![])
+            .spacing(12)
+            .align_y(Alignment::Center)
+            .push(text("Entry"))
+            .push(pick_list(entries, Some(self.entry.clone()), |e| {
+                Message(Msg::EntrySelected(e))
+            }));
+
+        if let Err(error) = &self.entries {
+            header = header.push(text(error.report()).size(12));
+        }
+        if !self.edits.is_empty() {
+            header = header.push(
+                text(format!(
+                    "{} value(s) edited, saved along with the other edits",
+                    self.edits.len()
+                ))
+                .size(12),
+            );
+        }
+
+        let content: Element<'_, Message> = match &self.content {
+            None => text("Reading the entry…").into(),
+            Some(Err(error)) => text(error.report()).into(),
+            Some(Ok(index)) => {
+                let mut rows = vec![];
+                self.tree_rows(&index.json, JsonPointer::root(), "(root)", 0, &mut rows);
+
+                row(vec![])
+                    .spacing(20)
+                    .push(
+                        scrollable(column(rows).spacing(2))
+                            .width(Length::FillPortion(3))
+                            .height(Length::Fill),
+                    )
+                    .push(
+                        scrollable(self.details(index))
+                            .width(Length::FillPortion(2))
+                            .height(Length::Fill),
+                    )
+                    .into()
+            }
+        };
+
+        column(vec![])
+            .spacing(12)
+            .padding(10)
+            .push(header)
+            .push(content)
+            .into()
+    }
+
+    /// The row of the node at `pointer`, followed by those of its children when expanded
+    fn tree_rows<'a>(
+        &self,
+        json: &Value,
+        pointer: JsonPointer,
+        key: &str,
+        depth: u16,
+        rows: &mut Vec<Element<'a, Message>>,
+    ) {
+        let expanded = self.expanded.contains(&pointer);
+        let is_container = json.is_object() || json.is_array();
+
+        let toggle: Element<'a, Message> = match is_container {
+            true => button(text(if expanded { "-" } else { "+" }).size(14))
+                .padding([0, 6])
+                .style(button::text)
+                .on_press(Message(Msg::Toggle(pointer.clone())))
+                .into(),
+            false => Space::with_width(Length::Fixed(22.0)).into(),
+        };
+
+        let summary = match self.edit(&pointer) {
+            Some(edit) => format!("{} (edited)", summarize(&edit.value)),
+            None => summarize(json),
+        };
+        let style = match self.selected.as_ref() == Some(&pointer) {
+            true => button::primary,
+            false => button::text,
+        };
+        let label = button(text(format!("{}: {}", key, summary)).size(14))
+            .padding([0, 4])
+            .style(style)
+            .on_press(Message(Msg::Select(pointer.clone())));
+
+        rows.push(
+            row(vec![])
+                .align_y(Alignment::Center)
+                .push(Space::with_width(Length::Fixed(f32::from(depth) * 16.0)))
+                .push(toggle)
+                .push(label)
+                .into(),
+        );
+
+        if !expanded {
+            return;
+        }
+
+        let children: Vec<(String, &Value)> = match json {
+            Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            Value::Array(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut hidden = 0;
+        for (position, (key, value)) in children.iter().enumerate() {
+            let child = pointer.child(key);
+
+            // A followed reference may lead past the children shown
+            let on_selected_path = matches!(&self.selected, Some(s) if s.starts_with(&child));
+            if position < MAX_CHILDREN || on_selected_path {
+                self.tree_rows(value, child, key, depth + 1, rows);
+            } else {
+                hidden += 1;
+            }
+        }
+        if hidden > 0 {
+            rows.push(
+                row(vec![])
+                    .push(Space::with_width(Length::Fixed(
+                        f32::from(depth + 1) * 16.0 + 22.0,
+                    )))
+                    .push(text(format!("… {} more", hidden)).size(14))
+                    .into(),
+            );
+        }
+    }
+
+    /// What there is to know about the selected node, and its edition
+    fn details<'a>(&self, index: &IndexedJson) -> Element<'a, Message> {
+        let pointer = match &self.selected {
+            Some(pointer) => pointer,
+            None => return text("Select a node to see its details").into(),
+        };
+        let json = match pointer.get(&index.json) {
+            Some(json) => json,
+            None => return text(format!("No value at '{}'", pointer)).into(),
+        };
+
+        let mut layout = column(vec![])
+            .spacing(6)
+            .push(text("Pointer").size(24))
+            .push(
+                row(vec![])
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(text(match pointer.is_root() {
+                        true => "(root)".to_string(),
+                        false => pointer.to_string(),
+                    }))
+                    .push(
+                        button(text("Copy").size(14))
+                            .padding([2, 8])
+                            .on_press(Message(Msg::CopyPointer)),
+                    ),
+            );
+
+        let follow = |label: String, target: JsonPointer| {
+            button(text(label).size(14))
+                .padding([0, 4])
+                .style(button::text)
+                .on_press(Message(Msg::Follow(target)))
+        };
+
+        if let Some(reference) = json.get("$ref").and_then(|r| r.as_str()) {
+            layout = layout.push(text("Reference").size(24));
+            layout = match index.dereference(json, pointer) {
+                Ok(target) => {
+                    let target_pointer = index.pointer_for(Id::from(reference));
+                    let label = format!("$id {}: {}", reference, summarize(target));
+
+                    match target_pointer {
+                        Ok(target_pointer) => layout.push(follow(label, target_pointer)),
+                        Err(_) => layout.push(text(label).size(14)),
+                    }
+                }
+                Err(error) => layout.push(text(error.to_string()).size(14)),
+            };
+        }
+
+        if let Some(id) = json.get("$id").and_then(|i| i.as_str()) {
+            let used_by = index.referenced_by(&Id::from(id));
+
+            if !used_by.is_empty() {
+                layout = layout.push(text("Used by").size(24)).push(text(
+                    "These references share this value, editing it edits it for all of them.",
+                ));
+                for reference in used_by.iter().take(MAX_CHILDREN) {
+                    layout = layout.push(follow(reference.to_string(), reference.clone()));
+                }
+            }
+        }
+
+        if json.is_object() || json.is_array() {
+            return layout.into();
+        }
+
+        layout = layout.push(text("Value").size(24));
+        if !self.is_editable() {
+            return layout
+                .push(text(json.to_string()))
+                .push(text("Only the header, party and player entries can be edited.").size(12))
+                .into();
+        }
+
+        let shared = index.shared_with(&JsonPatch::Pointer {
+            pointer: pointer.clone(),
+            new_value: json.clone(),
+        });
+        if !shared.is_empty() {
+            let used_by: Vec<_> = shared.iter().map(|p| format!("'{}'", p)).collect();
+            layout = layout.push(
+                text(format!(
+                    "This value is also used by {}, editing it edits it there too.",
+                    used_by.join(", ")
+                ))
+                .size(12),
+            );
+        }
+
+        // The loaded value, whose type is kept unless it's null
+        if json.is_null() {
+            layout = layout.push(
+                row(vec![])
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(text("Edit as"))
+                    .push(pick_list(ScalarType::ALL, Some(self.input_type), |t| {
+                        Message(Msg::TypeSelected(t))
+                    })),
+            );
+        }
+
+        layout = layout.push(
+            row(vec![])
+                .spacing(12)
+                .push(
+                    text_input("Value", &self.input)
+                        .on_input(|s| Message(Msg::InputChanged(s)))
+                        .on_submit(Message(Msg::Apply))
+                        .padding(4),
+                )
+                .push(button(text("Apply")).on_press(Message(Msg::Apply))),
+        );
+
+        if let Some(error) = &self.input_error {
+            layout = layout.push(text(error.clone()).size(12));
+        }
+        if let Some(edit) = self.edit(pointer) {
+            layout = layout.push(
+                row(vec![])
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(text(format!("Loaded as {}", edit.original)).size(12))
+                    .push(
+                        button(text("Revert").size(14))
+                            .padding([2, 8])
+                            .on_press(Message(Msg::Revert(pointer.clone()))),
+                    ),
+            );
+        }
+
+        layout.into()
+    }
+}
+
+/// The documents the editor saves, which are already loaded
+fn document(documents: &SaveDocuments, entry: &str) -> Option<Arc<IndexedJson>> {
+    match entry {
+        "header.json" => Some(documents.header.clone()),
+        "party.json" => Some(documents.party.clone()),
+        "player.json" => Some(documents.player.clone()),
+        _ => None,
+    }
+}
+
+/// A one line description of a node
+fn summarize(json: &Value) -> String {
+    match json {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(|r| r.as_str()) {
+                return format!("$ref {}", reference);
+            }
+
+            // `$type`s are written as `<type name>, <assembly>`
+            let tpe = map
+                .get("$type")
+                .and_then(|t| t.as_str())
+                .map(|t| t.split(", ").next().unwrap_or(t));
+            match tpe {
+                Some(tpe) => format!("{} ({} fields)", tpe, map.len()),
+                None => format!("{{{} fields}}", map.len()),
+            }
+        }
+        Value::Array(values) => format!("[{} items]", values.len()),
+        scalar => {
+            let mut s = scalar.to_string();
+            if s.chars().count() > 80 {
+                s = s.chars().take(79).chain(std::iter::once('…')).collect();
+            }
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::Fingerprint;
+
+    fn explorer(player: &str) -> ExplorerWidget {
+        let document = |json: &str| Arc::new(IndexedJson::new(serde_json::from_str(json).unwrap()));
+        let documents = SaveDocuments {
+            header: document("{}"),
+            party: document("{}"),
+            player: document(player),
+            fingerprint: Fingerprint::new(b"", None),
+            archive: Arc::from(&b""[..]),
+        };
+
+        ExplorerWidget::new(documents)
+    }
+
+    fn edit(explorer: &mut ExplorerWidget, pointer: JsonPointer, input: &str) {
+        let _ = explorer.update(Message(Msg::Select(pointer)));
+        let _ = explorer.update(Message(Msg::InputChanged(input.to_string())));
+        let _ = explorer.update(Message(Msg::Apply));
+    }
+
+    #[test]
+    fn scalar_edits_become_pointer_patches() {
+        let mut explorer = explorer(r#"{"$id":"1","Money":500,"Name":"Seelah","Level":3}"#);
+
+        edit(&mut explorer, "/Money".into(), "1000");
+        edit(&mut explorer, "/Name".into(), "Ember");
+        // Edits keep the type of the value, and going back to it drops them
+        edit(&mut explorer, "/Money".into(), "plenty");
+        assert!(explorer.input_error.is_some());
+        edit(&mut explorer, "/Level".into(), "4");
+        edit(&mut explorer, "/Level".into(), "3");
+
+        assert_eq!(
+            explorer.patches("player.json"),
+            vec![
+                JsonPatch::Pointer {
+                    pointer: "/Money".into(),
+                    new_value: 1000.into()
+                },
+                JsonPatch::Pointer {
+                    pointer: "/Name".into(),
+                    new_value: "Ember".into()
+                },
+            ]
+        );
+        assert!(explorer.patches("party.json").is_empty());
+    }
+
+    #[test]
+    fn null_values_can_be_edited_as_any_type() {
+        let mut explorer = explorer(r#"{"$id":"1","Title":null}"#);
+
+        let _ = explorer.update(Message(Msg::Select("/Title".into())));
+        let _ = explorer.update(Message(Msg::TypeSelected(ScalarType::Text)));
+        let _ = explorer.update(Message(Msg::InputChanged("Queen".to_string())));
+        let _ = explorer.update(Message(Msg::Apply));
+
+        assert_eq!(
+            explorer.patches("player.json"),
+            vec![JsonPatch::Pointer {
+                pointer: "/Title".into(),
+                new_value: "Queen".into()
+            }]
+        );
+
+        // Selected again, the edit is shown with its new type
+        let _ = explorer.update(Message(Msg::Select("/Title".into())));
+        assert_eq!(explorer.input_type, ScalarType::Text);
+        assert_eq!(explorer.input, "Queen");
+    }
+
+    #[test]
+    fn edits_of_keys_with_separators_are_escaped() {
+        let json = r#"{"$id":"1","a/b":{"c~d":1}}"#;
+        let mut explorer = explorer(json);
+
+        let pointer = JsonPointer::root().child("a/b").child("c~d");
+        edit(&mut explorer, pointer.clone(), "2");
+
+        let patches = explorer.patches("player.json");
+        assert_eq!(pointer.to_string(), "/a~1b/c~0d");
+        assert_eq!(JsonPointer::from("/a~1b/c~0d"), pointer);
+
+        let mut index = IndexedJson::new(serde_json::from_str(json).unwrap());
+        index.patch_all(&patches).unwrap();
+        assert_eq!(index.json["a/b"]["c~d"], 2);
+    }
+
+    #[test]
+    fn entries_are_read_from_the_loaded_save() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        for (name, json) in [("player.json", "{}"), ("area.json", r#"{"Doors":3}"#)].iter() {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut zip, json.as_bytes()).unwrap();
+        }
+        let mut explorer = explorer(r#"{"$id":"1","Money":500}"#);
+        explorer.documents.archive = zip.finish().unwrap().into_inner().into();
+        let _ = explorer.set_documents(explorer.documents.clone());
+
+        assert_eq!(
+            explorer.entries,
+            Ok(vec!["area.json".to_string(), "player.json".to_string()])
+        );
+
+        // Read once, as it was when loaded
+        let _ = explorer.update(Message(Msg::EntrySelected("area.json".to_string())));
+        assert!(explorer.content.is_none());
+        let area = save::read_json_entry(&explorer.documents, "area.json").unwrap();
+        assert_eq!(area.json["Doors"], 3);
+        let content = Content(Arc::new(area));
+        let _ = explorer.update(Message(Msg::EntryRead(
+            "area.json".to_string(),
+            Box::new(Ok(content)),
+        )));
+        let _ = explorer.update(Message(Msg::EntrySelected("player.json".to_string())));
+        let _ = explorer.update(Message(Msg::EntrySelected("area.json".to_string())));
+        assert!(explorer.index().is_some());
+    }
+
+    #[test]
+    fn saved_edits_are_dropped_with_the_documents_they_are_in() {
+        let mut explorer = explorer(r#"{"$id":"1","Money":500,"Level":3}"#);
+        edit(&mut explorer, "/Money".into(), "1000");
+        edit(&mut explorer, "/Level".into(), "4");
+
+        let mut documents = explorer.documents.clone();
+        Arc::make_mut(&mut documents.player).json["Money"] = 1000.into();
+        let _ = explorer.set_documents(documents);
+
+        assert_eq!(explorer.index().unwrap().json["Money"], 1000);
+        assert_eq!(
+            explorer.patches("player.json"),
+            vec![JsonPatch::Pointer {
+                pointer: "/Level".into(),
+                new_value: 4.into()
+            }]
+        );
+    }
+
+    #[test]
+    fn followed_references_are_shown_past_the_children_limit() {
+        let items: Vec<_> = (0..MAX_CHILDREN + 50).collect();
+        let json = serde_json::json!({ "$id": "1", "Items": items }).to_string();
+        let mut explorer = explorer(&json);
+
+        let rows = |explorer: &ExplorerWidget| {
+            let mut rows = vec![];
+            let index = explorer.index().unwrap();
+            explorer.tree_rows(&index.json, JsonPointer::root(), "(root)", 0, &mut rows);
+            rows.len()
+        };
+
+        let _ = explorer.update(Message(Msg::Toggle(JsonPointer::root())));
+        let _ = explorer.update(Message(Msg::Toggle("/Items".into())));
+        // The root, its 2 children, the shown items and the count of the others
+        assert_eq!(rows(&explorer), 3 + MAX_CHILDREN + 1);
+
+        let _ = explorer.update(Message(Msg::Follow("/Items/240".into())));
+        assert_eq!(rows(&explorer), 3 + MAX_CHILDREN + 2);
+    }
+}
@@ -0,0 +1,196 @@
+use super::SavePreview;
+use crate::data::{self, Header, SaveFormat, SaveType};
+use crate::json::{JsonPatch, Value};
+use crate::theme::BOOKLETTER_1911;
+use iced::{
+    widget::{checkbox, column, row, text, text_input},
+    Element, Length, Task,
+};
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    NameChanged(String),
+    DescriptionChanged(String),
+    SyncCharacterName(bool),
+}
+
+/// Everything we know about the save itself, as opposed to its content.
+/// The name and description shown in the game's load menu can be edited.
+pub struct HeaderWidget {
+    header: Header,
+    preview: Option<SavePreview>,
+    name: String,
+    description: String,
+    sync_character_name: bool,
+}
+
+impl HeaderWidget {
+    pub fn new(header: Header, preview: Option<SavePreview>) -> HeaderWidget {
+        HeaderWidget {
+            name: header.name.clone(),
+            description: header.description.clone().unwrap_or_default(),
+            sync_character_name: true,
+            header,
+            preview,
+        }
+    }
+
+    /// Whether renaming the main character should also rename the
+    /// character shown in the game's load menu.
+    pub fn sync_character_name(&self) -> bool {
+        self.sync_character_name
+    }
+
+    /// Apply the edits made in `previous` to this header, read from a newer save
+    pub fn carry_over(&mut self, previous: &HeaderWidget) {
+        if previous.name != previous.header.name {
+            self.name = previous.name.clone();
+        }
+        if previous.description != previous.header.description.clone().unwrap_or_default() {
+            self.description = previous.description.clone();
+        }
+        self.sync_character_name = previous.sync_character_name;
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message(Msg::NameChanged(name)) => self.name = name,
+            Message(Msg::DescriptionChanged(description)) => self.description = description,
+            Message(Msg::SyncCharacterName(sync)) => self.sync_character_name = sync,
+        };
+
+        Task::none()
+    }
+
+    /// Only the fields which have been modified are patched. Without a name
+    /// of our own, the save will be named after the original one.
+    pub fn patches(&self) -> Vec<JsonPatch> {
+        let mut patches = vec![];
+
+        if self.name != self.header.name {
+            patches.push(JsonPatch::string("/Name".into(), self.name.clone()));
+        }
+
+        if self.description != self.header.description.clone().unwrap_or_default() {
+            let description = match self.description.is_empty() {
+                true => Value::Null,
+                false => Value::from(self.description.clone()),
+            };
+
+            patches.push(JsonPatch::Pointer {
+                pointer: "/Description".into(),
+                new_value: description,
+            });
+        }
+
+        patches
+    }
+
+    /// `name_hint` tells what the save is named when its name isn't edited
+    pub fn view(&self, name_hint: String) -> Element<'_, Message> {
+        let header = &self.header;
+        let or_dash = |s: Option<String>| s.unwrap_or_else(|| "-".to_string());
+
+        let save_type = match (header.save_type, header.quick_save_number) {
+            (Some(SaveType::Quick), Some(n)) => format!("Quick (#{})", n),
+            (Some(t), _) => t.to_string(),
+            (None, _) => "-".to_string(),
+        };
+
+        let fields = [
+            ("Character", or_dash(header.player_character_name.clone())),
+            ("Type", save_type),
+            (
+                "Auto level-up save",
+                if header.is_auto_levelup_save {
+                    "Yes"
+                } else {
+                    "No"
+                }
+                .to_string(),
+            ),
+            (
+                "Saved at",
+                or_dash(
+                    header
+                        .system_save_time
+                        .map(|t| t.format("%Y-%m-%d %H:%M:%S %:z").to_string()),
+                ),
+            ),
+            (
+                "In-game time",
+                or_dash(header.game_save_time.as_ref().map(data::format_game_time)),
+            ),
+            (
+                "Play time",
+                or_dash(header.game_total_time.as_ref().map(data::format_game_time)),
+            ),
+            ("Area", or_dash(header.area.clone())),
+            ("Area part", or_dash(header.area_part.clone())),
+            ("Area name", or_dash(header.area_name_override.clone())),
+            ("Campaign", or_dash(header.game_id.clone())),
+            ("DLC campaign", or_dash(header.dlc_campaign.clone())),
+            (
+                "Endless delve seed",
+                or_dash(header.endless_delve_seed.map(|s| s.to_string())),
+            ),
+            ("Compatibility version", SaveFormat::of(header).to_string()),
+            (
+                "Versions",
+                header
+                    .versions
+                    .iter()
+                    .map(u64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ];
+
+        let name_hint = match self.name == header.name {
+            true => name_hint,
+            false => String::new(),
+        };
+
+        let mut layout = column(vec![])
+            .spacing(6)
+            .push(
+                text_input("Save name", &self.name)
+                    .on_input(|s| Message(Msg::NameChanged(s)))
+                    .font(BOOKLETTER_1911)
+                    .size(30)
+                    .padding(4),
+            )
+            .push(text(name_hint).size(12))
+            .push(
+                text_input("Description", &self.description)
+                    .on_input(|s| Message(Msg::DescriptionChanged(s)))
+                    .padding(4),
+            )
+            .push(
+                checkbox(
+                    "Rename the save's character along with the main character",
+                    self.sync_character_name,
+                )
+                .on_toggle(|b| Message(Msg::SyncCharacterName(b))),
+            );
+
+        if let Some(preview) = &self.preview {
+            layout = layout
+                .push(preview.thumbnail(480.0))
+                .push(preview.portraits(48.0));
+        }
+
+        for (label, value) in fields {
+            layout = layout.push(
+                row(vec![])
+                    .push(text(label).width(Length::Fixed(200.0)))
+                    .push(text(value)),
+            );
+        }
+
+        layout.into()
+    }
+}
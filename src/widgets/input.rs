@@ -1,3 +1,7 @@
+// iced 0.13 deprecated components in favour of custom widgets. We still rely on them
+// until the input is rewritten, so the deprecation lint is silenced for this module.
+#![allow(deprecated)]
+
 use iced::widget::{row, text_input};
 use iced::Length;
 use iced::{widget::Component, Element};
@@ -67,7 +71,7 @@ where
 
     /// Produces the widgets of the [`Component`], which may trigger an [`Event`](Component::Event)
     /// on user interaction.
-    fn view(&self, _state: &Self::State) -> Element<Self::Event> {
+    fn view(&self, _state: &Self::State) -> Element<'_, Self::Event> {
         let label_widget = text_input(&self.label, &self.label)
             .size(16)
             .style(|theme, _status| {
@@ -92,3 +96,13 @@ where
             .into()
     }
 }
+
+impl<'a, V, Message> From<LabelledInputNumber<V, Message>> for Element<'a, Message>
+where
+    V: ToString + FromStr + 'a,
+    Message: 'a,
+{
+    fn from(input: LabelledInputNumber<V, Message>) -> Self {
+        iced::widget::component(input)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::dialog::{self, OpenError};
+use crate::save::{SaveError, SavingDone};
+use crate::theme;
+use iced::{
+    widget::{button, column, container, row, text, Space},
+    Alignment, Element, Length, Task,
+};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Message(Msg);
+
+#[derive(Debug, Clone)]
+enum Msg {
+    OpenFolder(PathBuf),
+    FolderOpened(Result<(), OpenError>),
+    ToggleDetails,
+    CopyDetails,
+    Dismiss,
+}
+
+impl Message {
+    /// Whether the user is done with the notification
+    pub fn dismissed(&self) -> bool {
+        matches!(self, Message(Msg::Dismiss))
+    }
+}
+
+enum Outcome {
+    Saved(SavingDone),
+    Failed {
+        error: SaveError,
+        /// What was being saved, to give some context to the details
+        archive_path: PathBuf,
+        show_details: bool,
+    },
+}
+
+/// The outcome of the latest save, until the user dismisses it
+#[derive(Default)]
+pub struct SaveNotification {
+    outcome: Option<Outcome>,
+}
+
+impl SaveNotification {
+    pub fn saved(&mut self, done: SavingDone) {
+        self.outcome = Some(Outcome::Saved(done));
+    }
+
+    pub fn failed(&mut self, error: SaveError, archive_path: PathBuf) {
+        self.outcome = Some(Outcome::Failed {
+            error,
+            archive_path,
+            show_details: false,
+        });
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message(Msg::OpenFolder(directory)) => {
+                Task::perform(dialog::open_folder(directory), |r| {
+                    Message(Msg::FolderOpened(r))
+                })
+            }
+            Message(Msg::FolderOpened(Ok(()))) => Task::none(),
+            Message(Msg::FolderOpened(Err(error))) => {
+                log::warn!("Couldn't open the folder: {}", error);
+                Task::none()
+            }
+            Message(Msg::ToggleDetails) => {
+                if let Some(Outcome::Failed { show_details, .. }) = &mut self.outcome {
+                    *show_details = !*show_details;
+                }
+                Task::none()
+            }
+            Message(Msg::CopyDetails) => match &self.outcome {
+                Some(Outcome::Failed {
+                    error,
+                    archive_path,
+                    ..
+                }) => iced::clipboard::write(details(error, archive_path)),
+                _ => Task::none(),
+            },
+            Message(Msg::Dismiss) => {
+                self.outcome = None;
+                Task::none()
+            }
+        }
+    }
+
+    pub fn view(&self) -> Option<Element<'_, Message>> {
+        let small_button = |label| button(text(label).size(14)).padding([2, 8]);
+        let dismiss = small_button("Dismiss").on_press(Message(Msg::Dismiss));
+
+        let content: Element<'_, Message> = match self.outcome.as_ref()? {
+            Outcome::Saved(done) => {
+                let summary = text(format!(
+                    "Saved to {} ({} KiB, {} KiB before editing)",
+                    done.path.display(),
+                    done.size_after / 1024,
+                    done.size_before / 1024
+                ));
+
+                let mut actions = row(vec![]).spacing(8);
+                if let Some(directory) = folder(&done.path) {
+                    actions = actions.push(
+                        small_button("Open folder").on_press(Message(Msg::OpenFolder(directory))),
+                    );
+                }
+
+                row(vec![])
+                    .spacing(12)
+                    .align_y(Alignment::Center)
+                    .push(summary)
+                    .push(Space::with_width(Length::Fill))
+                    .push(actions.push(dismiss))
+                    .into()
+            }
+            Outcome::Failed {
+                error,
+                archive_path,
+                show_details,
+            } => {
+                let toggle_label = match show_details {
+                    true => "Hide details",
+                    false => "Show details",
+                };
+
+                let mut layout = column(vec![]).spacing(6).push(
+                    row(vec![])
+                        .spacing(12)
+                        .align_y(Alignment::Center)
+                        .push(text(format!(
+                            "Saving failed, your edits are kept: {}",
+                            error.report()
+                        )))
+                        .push(Space::with_width(Length::Fill))
+                        .push(
+                            row(vec![])
+                                .spacing(8)
+                                .push(
+                                    small_button(toggle_label)
+                                        .on_press(Message(Msg::ToggleDetails)),
+                                )
+                                .push(
+                                    small_button("Copy details")
+                                        .on_press(Message(Msg::CopyDetails)),
+                                )
+                                .push(dismiss),
+                        ),
+                );
+
+                if *show_details {
+                    layout = layout.push(text(details(error, archive_path)).size(12));
+                }
+
+                layout.into()
+            }
+        };
+
+        let style = match self.outcome {
+            Some(Outcome::Failed { .. }) => theme::failure_notification,
+            _ => theme::success_notification,
+        };
+
+        Some(
+            container(content)
+                .width(Length::Fill)
+                .padding(10)
+                .style(style)
+                .into(),
+        )
+    }
+}
+
+/// Enough to report the failure, without the user having to dig through logs
+fn details(error: &SaveError, archive_path: &Path) -> String {
+    format!(
+        "Save: {}\nError: {}\nDetails: {:?}\nEditor version: {}",
+        archive_path.display(),
+        error.report(),
+        error,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// The folder of `path`, when it has one
+fn folder(path: &Path) -> Option<PathBuf> {
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
@@ -49,6 +49,7 @@ impl Display for KingdomResourcesField {
 pub struct PlayerWidget {
     player_id: Id,
     money: u64,
+    original_money: u64,
     resources: Option<KingdomResourcesState>,
     resources_per_turn: Option<KingdomResourcesState>,
     armies: Vec<ArmyState>,
@@ -61,6 +62,7 @@ impl PlayerWidget {
         PlayerWidget {
             player_id: player.id.clone(),
             money: player.money,
+            original_money: player.money,
             resources: player
                 .kingdom
                 .as_ref()
@@ -75,27 +77,20 @@ impl PlayerWidget {
 
     // TODO We are missing recruits panels
     // TODO We might need a scrollable widget to account for many army blocks
-    pub fn view(&self) -> Element<Message> {
-        let money = iced::widget::component(labelled_input_number(
-            "Money",
-            self.money,
-            move |new_value| Message(Msg::FieldUpdate(Field::Money, new_value)),
-        ));
+    pub fn view(&self) -> Element<'_, Message> {
+        let money: Element<_> = labelled_input_number("Money", self.money, move |new_value| {
+            Message(Msg::FieldUpdate(Field::Money, new_value))
+        })
+        .into();
 
         let mut resources = vec![];
-        match &self.resources {
-            Some(res) => {
-                let v = res.view("Resources", Field::Resources);
-                resources.push(v);
-            }
-            None => (),
+        if let Some(res) = &self.resources {
+            let v = res.view("Resources", Field::Resources);
+            resources.push(v);
         };
-        match &self.resources_per_turn {
-            Some(res) => {
-                let v = res.view("Resources/turn", Field::ResourcesPerTurn);
-                resources.push(v);
-            }
-            None => (),
+        if let Some(res) = &self.resources_per_turn {
+            let v = res.view("Resources/turn", Field::ResourcesPerTurn);
+            resources.push(v);
         };
 
         let armies = two_columns_layout(self.armies.iter().map(|a| a.view()));
@@ -160,12 +155,17 @@ impl PlayerWidget {
         self.armies.iter_mut().find(|a| a.army_id == army_id)
     }
 
+    /// Only the modified values are patched
     pub fn patches(&self) -> Vec<JsonPatch> {
-        let mut patches = vec![JsonPatch::id_at_pointer(
-            self.player_id.clone(),
-            "/Money".into(),
-            serde_json::to_value(self.money).unwrap(),
-        )];
+        let mut patches = vec![];
+
+        if self.money != self.original_money {
+            patches.push(JsonPatch::id_at_pointer(
+                self.player_id.clone(),
+                "/Money".into(),
+                serde_json::to_value(self.money).unwrap(),
+            ));
+        }
 
         if let Some(res) = self.resources.as_ref() {
             patches.append(&mut res.patches());
@@ -177,6 +177,39 @@ impl PlayerWidget {
 
         patches
     }
+
+    /// Apply the edits made in `previous` to this player, read from a newer save
+    pub fn carry_over(&mut self, previous: &PlayerWidget) {
+        if previous.money != previous.original_money {
+            self.money = previous.money;
+        }
+
+        if let (Some(current), Some(previous)) = (&mut self.resources, &previous.resources) {
+            current.carry_over(previous);
+        }
+        if let (Some(current), Some(previous)) =
+            (&mut self.resources_per_turn, &previous.resources_per_turn)
+        {
+            current.carry_over(previous);
+        }
+
+        for (position, army) in previous.armies.iter().enumerate() {
+            match self
+                .armies
+                .get_mut(position)
+                .filter(|a| a.same_composition(army))
+            {
+                Some(current) => current.carry_over(army),
+                None if army.is_modified() => {
+                    log::warn!(
+                        "Army {} not found anymore, its edits are lost",
+                        army.army_id
+                    )
+                }
+                None => (),
+            }
+        }
+    }
 }
 
 /// Organize `children` elements in a column containing two elements
@@ -216,6 +249,7 @@ struct KingdomResourcesState {
     finances: u64,
     materials: u64,
     favors: u64,
+    original: KingdomResources,
 }
 
 impl KingdomResourcesState {
@@ -225,11 +259,16 @@ impl KingdomResourcesState {
             finances: resources.finances,
             materials: resources.materials,
             favors: resources.favors,
+            original: resources.clone(),
         }
     }
 
     fn patches(&self) -> Vec<JsonPatch> {
-        let base = |pointer: &str, value| {
+        let base = |pointer: &str, value, original| {
+            if value == original {
+                return JsonPatch::None;
+            }
+
             JsonPatch::id_at_pointer(
                 self.resources_id.clone(),
                 pointer.into(),
@@ -238,26 +277,37 @@ impl KingdomResourcesState {
         };
 
         let patches = vec![
-            base("m_Finances", self.finances),
-            base("m_Materials", self.finances),
-            base("m_Favors", self.finances),
+            base("/m_Finances", self.finances, self.original.finances),
+            base("/m_Materials", self.materials, self.original.materials),
+            base("/m_Favors", self.favors, self.original.favors),
         ];
 
         patches
     }
 
-    fn view<F>(&self, title: &'static str, build_field: F) -> Element<Message>
+    fn carry_over(&mut self, previous: &KingdomResourcesState) {
+        if previous.finances != previous.original.finances {
+            self.finances = previous.finances;
+        }
+        if previous.materials != previous.original.materials {
+            self.materials = previous.materials;
+        }
+        if previous.favors != previous.original.favors {
+            self.favors = previous.favors;
+        }
+    }
+
+    fn view<F>(&self, title: &'static str, build_field: F) -> Element<'_, Message>
     where
         F: 'static + Clone + Fn(KingdomResourcesField) -> Field, // TODO is 'static and Clone still required ?
     {
-        let view = move |field: KingdomResourcesField, value| {
+        let view = move |field: KingdomResourcesField, value| -> Element<'_, Message> {
             let build_field = build_field.clone();
 
-            iced::widget::component(labelled_input_number(
-                field.to_string(),
-                value,
-                move |new_value| Message(Msg::FieldUpdate(build_field(field), new_value)),
-            ))
+            labelled_input_number(field.to_string(), value, move |new_value| {
+                Message(Msg::FieldUpdate(build_field(field), new_value))
+            })
+            .into()
         };
 
         let layout = column(vec![])
@@ -287,6 +337,7 @@ struct ArmyState {
     /// name (or rather, newtype could implement the correct [Display]
     /// for it)
     squads: Vec<Squad>,
+    original: Army,
 }
 
 impl ArmyState {
@@ -299,19 +350,20 @@ impl ArmyState {
             army_id,
             movement_points,
             squads,
+            original: army.clone(),
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         // I don't know of a cleaner pattern to share the same immutable variable with multiple closures.
         // I feel there should be a simpler pattern than having multiple named variable but that will have
         // to do for now.
         let army_id = self.army_id.clone();
-        let common = row(vec![]).push(iced::widget::component(labelled_input_number(
+        let common = row(vec![]).push(labelled_input_number(
             "Movement Points",
             self.movement_points,
             move |v| Message(Msg::ArmyMovementPointsUpdate(army_id.clone(), v)),
-        )));
+        ));
 
         let mut layout = column(vec![]).push(common);
 
@@ -324,11 +376,9 @@ impl ArmyState {
                 None => unit,
             };
 
-            layout = layout.push(iced::widget::component(labelled_input_number(
-                label,
-                *count,
-                move |v| Message(Msg::ArmySquadUpdate(army_id.clone(), squad_id.clone(), v)),
-            )));
+            layout = layout.push(labelled_input_number(label, *count, move |v| {
+                Message(Msg::ArmySquadUpdate(army_id.clone(), squad_id.clone(), v))
+            }));
         }
         let inner = container(layout).padding(5).style(theme::army_widget);
 
@@ -346,24 +396,54 @@ impl ArmyState {
         }
     }
 
+    fn is_modified(&self) -> bool {
+        self.movement_points != self.original.movement_points || self.squads != self.original.squads
+    }
+
+    /// Armies don't have an identifier stable across saves, the game renumbers
+    /// the `$id`s every time. Their position in the list of armies and their
+    /// composition both matching is the best we can do.
+    fn same_composition(&self, other: &ArmyState) -> bool {
+        let units = |squads: &[Squad]| squads.iter().map(|s| s.unit.clone()).collect::<Vec<_>>();
+
+        units(&self.original.squads) == units(&other.original.squads)
+    }
+
+    fn carry_over(&mut self, previous: &ArmyState) {
+        if previous.movement_points != previous.original.movement_points {
+            self.movement_points = previous.movement_points;
+        }
+
+        let squads = self.squads.iter_mut().zip(&previous.squads);
+        for ((current, edited), original) in squads.zip(&previous.original.squads) {
+            if edited.count != original.count {
+                current.count = edited.count;
+            }
+        }
+    }
+
     fn patches(&self) -> Vec<JsonPatch> {
         let mut patches: Vec<_> = self
             .squads
             .iter()
-            .map(|s| {
+            .zip(&self.original.squads)
+            .filter(|(s, original)| s.count != original.count)
+            .map(|(s, _)| {
                 JsonPatch::id_at_pointer(
                     s.id.clone(),
-                    "Count".into(),
+                    "/Count".into(),
                     serde_json::to_value(s.count).unwrap(),
                 )
             })
             .collect();
 
-        patches.push(JsonPatch::id_at_pointer(
-            self.army_id.clone(),
-            "MovementPoints".into(),
-            serde_json::to_value(self.movement_points).unwrap(),
-        ));
+        if self.movement_points != self.original.movement_points {
+            patches.push(JsonPatch::id_at_pointer(
+                self.army_id.clone(),
+                "/MovementPoints".into(),
+                serde_json::to_value(self.movement_points).unwrap(),
+            ));
+        }
 
         patches
     }
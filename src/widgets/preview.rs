@@ -0,0 +1,107 @@
+use crate::data;
+use crate::save::{self, SaveSummary};
+use iced::{
+    widget::{container, image, row, text, tooltip, Image, Space},
+    Alignment, Element,
+};
+
+/// What lets us recognize a save at a glance: the screenshot taken by the game
+/// and the portraits of the party.
+///
+/// Image handles are created once, otherwise the images would be decoded and
+/// uploaded again on each frame.
+#[derive(Debug, Clone)]
+pub struct SavePreview {
+    name: String,
+    details: String,
+    thumbnail: Option<image::Handle>,
+    portraits: Vec<Portrait>,
+}
+
+#[derive(Debug, Clone)]
+struct Portrait {
+    name: String,
+    image: Option<image::Handle>,
+}
+
+impl SavePreview {
+    pub fn new(summary: &SaveSummary) -> SavePreview {
+        let thumbnail = summary
+            .thumbnail
+            .as_ref()
+            .map(|png| image::Handle::from_bytes(png.clone()));
+
+        // Portraits from the game are packed in its assets, only the ones
+        // provided by the user can be found on disk.
+        let portraits = summary
+            .header
+            .party_portraits
+            .iter()
+            .map(|portrait| Portrait {
+                name: portrait.name(),
+                image: portrait
+                    .custom_portrait_id()
+                    .and_then(save::custom_portrait)
+                    .map(image::Handle::from_path),
+            })
+            .collect();
+
+        let header = &summary.header;
+        let details = vec![
+            header.player_character_name.clone(),
+            header
+                .game_total_time
+                .as_ref()
+                .map(|t| format!("played {}", data::format_game_time(t))),
+            header
+                .system_save_time
+                .map(|t| format!("saved {}", t.format("%Y-%m-%d %H:%M"))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" – ");
+
+        SavePreview {
+            name: header.name.clone(),
+            details,
+            thumbnail,
+            portraits,
+        }
+    }
+
+    /// Name of the save, as given in game
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Character, play time and date of the save, on a single line
+    pub fn details(&self) -> &str {
+        &self.details
+    }
+
+    /// The screenshot, or an empty space of the same size when there is none
+    pub fn thumbnail<'a, M: 'a>(&'a self, width: f32) -> Element<'a, M> {
+        match &self.thumbnail {
+            Some(handle) => Image::new(handle.clone()).width(width).into(),
+            None => Space::new(width, width * 9.0 / 16.0).into(),
+        }
+    }
+
+    /// The party portraits, falling back to the companion's name when the
+    /// image isn't available.
+    pub fn portraits<'a, M: 'a>(&'a self, size: f32) -> Element<'a, M> {
+        let portraits = self.portraits.iter().map(|portrait| match &portrait.image {
+            Some(handle) => tooltip(
+                Image::new(handle.clone()).height(size),
+                text(&portrait.name).size(12),
+                tooltip::Position::Bottom,
+            )
+            .style(container::rounded_box)
+            .into(),
+            None => text(&portrait.name).size(12).into(),
+        });
+
+        row(portraits).spacing(6).align_y(Alignment::Center).into()
+    }
+}